@@ -120,6 +120,14 @@ impl PartialEq for ByteStr {
     }
 }
 
+impl Eq for ByteStr {}
+
+impl std::hash::Hash for ByteStr {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state);
+    }
+}
+
 impl PartialEq<str> for ByteStr {
     fn eq(&self, other: &str) -> bool {
         str::eq(self, other)
@@ -33,6 +33,8 @@
 //! - [`String`]
 //! - [`Form`]
 //! - [`Json`]
+//! - [`Query`]
+//! - [`Range`]
 //! - [`Request`]
 //!
 //! # Responder
@@ -45,6 +47,7 @@
 //! - [`String`]
 //! - [`Html`]
 //! - [`Json`]
+//! - [`Ranged`]
 //! - [`Response`]
 //!
 //! # Routing
@@ -66,6 +69,10 @@
 //!
 //! - [`HttpService`]
 //! - [`BodyLimit`]
+//! - [`Cors`]
+//! - [`Compression`]
+//! - [`RequestDecompression`]
+//! - [`ServeDir`] and [`ServeFile`]
 //! - [`ServiceFn`]
 //!
 //! # Runtime
@@ -104,6 +111,8 @@
 //!
 //! Middleware is a logic that runs against a request before reaching a handler. Middleware can
 //! modify, validate, or reject a request.
+//! Using [`from_fn`], user can create a middleware from an async function, which then applied
+//! using [`Router::layer`].
 //!
 //! ## Handler
 //!
@@ -132,6 +141,15 @@
 //! [`IntoResponseParts`]: crate::response::IntoResponseParts
 //! [`Router`]: crate::route::Router
 //! [`Service`]: crate::service::Service
+//! [`BodyLimit`]: crate::helpers::BodyLimit
+//! [`from_fn`]: crate::middleware::from_fn
+//! [`Cors`]: crate::middleware::Cors
+//! [`Compression`]: crate::middleware::Compression
+//! [`RequestDecompression`]: crate::middleware::RequestDecompression
+//! [`ServeDir`]: crate::service::fs::ServeDir
+//! [`ServeFile`]: crate::service::fs::ServeFile
+//! [`Range`]: crate::helpers::Range
+//! [`Ranged`]: crate::helpers::Ranged
 
 // impl Future vs type Future vs generic Future
 // - impl Future: can be async fn, type cannot be referenced externally, no double implementation
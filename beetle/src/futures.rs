@@ -64,16 +64,6 @@ pub trait TryFutureExt: Future {
     {
         MapOk { inner: self, mapper: Some(mapper)  }
     }
-
-    /// map future output if its `Err`
-    fn map_err<M,T,E,E2>(self, mapper: M) -> MapErr<Self,M>
-    where
-        Self: Future<Output = Result<T,E>>,
-        M: FnOnce(E) -> E2,
-        Self: Sized,
-    {
-        MapErr { inner: self, mapper: Some(mapper)  }
-    }
 }
 
 impl<F> TryFutureExt for F where F: Future { }
@@ -98,7 +88,8 @@ where
 
     fn poll(self: std::pin::Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<Self::Output> {
         let me = self.project();
-        Poll::Ready((me.mapper.take().expect("poll after complete"))(ready!(me.inner.poll(cx))))
+        let output = ready!(me.inner.poll(cx));
+        Poll::Ready((me.mapper.take().expect("poll after complete"))(output))
     }
 }
 
@@ -238,34 +229,3 @@ where
         }
     }
 }
-
-// ---
-
-pin_project_lite::pin_project! {
-    /// map the output of a future
-    #[derive(Debug)]
-    pub struct MapErr<F,M> {
-        #[pin]
-        inner: F,
-        mapper: Option<M>,
-    }
-}
-
-impl<F,M,T,E,E2> Future for MapErr<F,M>
-where
-    F: Future<Output = Result<T,E>>,
-    M: FnOnce(E) -> E2,
-{
-    type Output = Result<T,E2>;
-
-    fn poll(self: std::pin::Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<Self::Output> {
-        let me = self.project();
-        match ready!(me.inner.poll(cx)) {
-            Ok(ok) => Ready(Ok(ok)),
-            Err(err) => Ready(Err(me.mapper.take().expect("poll after complete")(err))),
-        }
-    }
-}
-
-
-
@@ -19,6 +19,10 @@ struct EntryExtra {
     next: *mut EntryExtra,
 }
 
+// SAFETY: `Entry` uniquely owns its linked extra values, the same way `Box` would
+unsafe impl Send for Entry { }
+unsafe impl Sync for Entry { }
+
 impl Entry {
     pub fn new(hash: Size, name: HeaderName, value: HeaderValue) -> Self {
         Self {
@@ -107,6 +111,10 @@ pub struct GetAll<'a> {
     next: *const EntryExtra,
 }
 
+// SAFETY: `GetAll` only hands out shared references borrowed from `Entry`
+unsafe impl Send for GetAll<'_> { }
+unsafe impl Sync for GetAll<'_> { }
+
 impl<'a> GetAll<'a> {
     pub fn new(entry: &'a Entry) -> Self {
         Self {
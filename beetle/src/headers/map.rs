@@ -13,7 +13,6 @@ use super::{
 type Size = u16;
 
 /// HTTP Headers Multimap.
-#[derive(Default)]
 pub struct HeaderMap {
     indices: Box<[Option<Size>]>,
     entries: Vec<Entry>,
@@ -59,8 +58,13 @@ impl HeaderMap {
         self.entries.len() + self.extra_len as usize
     }
 
+    /// Returns `true` if the map contains no headers.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
     /// Returns an iterator over the headers.
-    pub fn iter(&self) -> Iter {
+    pub fn iter(&self) -> Iter<'_> {
         Iter::new(self)
     }
 
@@ -68,10 +72,6 @@ impl HeaderMap {
         &self.entries
     }
 
-    pub(crate) fn entries_mut(&mut self) -> &mut Vec<Entry> {
-        &mut self.entries
-    }
-
     /// Returns `true` if the map contains a header value for the header key.
     pub fn contains_key(&self, name: &HeaderName) -> bool {
         self.get(name).is_some()
@@ -105,11 +105,11 @@ impl HeaderMap {
     }
 
     /// Returns a reference to all header values corresponding to the header name.
-    pub fn get_all<K: AsHeaderName>(&self, name: K) -> GetAll {
+    pub fn get_all<K: AsHeaderName>(&self, name: K) -> GetAll<'_> {
         self.try_get_all(name.to_header_ref())
     }
 
-    pub fn try_get_all(&self, name: HeaderNameRef) -> GetAll {
+    pub fn try_get_all(&self, name: HeaderNameRef) -> GetAll<'_> {
         if self.entries.is_empty() {
             return GetAll::empty();
         }
@@ -148,38 +148,47 @@ impl HeaderMap {
         let hash = name.hash();
         let mut index = hash & (mask - 1);
 
-        loop {
-            let entry_index = self.indices[index as usize]? as usize;
-            let entry = &self.entries[entry_index];
+        let entry_index = loop {
+            let entry_index = self.indices[index as usize]?;
+            let entry = &self.entries[entry_index as usize];
 
             if entry.hash() == &hash && entry.name().as_str() == name.as_str() {
-                if let Some(last_entry) = self.entries.last() {
-                    if last_entry.hash() != entry.hash() {
-                        let mut index = last_entry.hash() & (mask - 1);
-
-                        loop {
-                            let inner_entry_index = self.indices[index as usize].as_mut().unwrap();
-                            let inner_entry = &self.entries[*inner_entry_index as usize];
-                            if inner_entry.hash() == last_entry.hash()
-                                && inner_entry.name().as_str() == last_entry.name().as_str()
-                            {
-                                *inner_entry_index = entry_index as Size;
-                                break;
-                            }
-                            index = (index + 1) & (mask - 1);
-                        }
-                    }
-                }
-                let entry_index = self.indices[index as usize].take().unwrap();
-                let entry = self.entries.swap_remove(entry_index as usize);
-                self.extra_len -= entry.extra_len();
-                let (_,value) = entry.into_parts();
-                return Some(value);
+                break entry_index;
             }
 
             // Remove Collision
             index = (index + 1) & (mask - 1);
+        };
+
+        // last entry will be swapped into the removed entry position
+        let last_index = (self.entries.len() - 1) as Size;
+        if last_index != entry_index {
+            let mut index = self.entries[last_index as usize].hash() & (mask - 1);
+            while self.indices[index as usize] != Some(last_index) {
+                index = (index + 1) & (mask - 1);
+            }
+            self.indices[index as usize] = Some(entry_index);
+        }
+
+        self.indices[index as usize] = None;
+        let entry = self.entries.swap_remove(entry_index as usize);
+
+        // backward shift subsequent collided entries so probing does not stop at the hole
+        let mut hole = index;
+        let mut next = (hole + 1) & (mask - 1);
+        while let Some(shifted) = self.indices[next as usize] {
+            let ideal = self.entries[shifted as usize].hash() & (mask - 1);
+            if next.wrapping_sub(ideal) & (mask - 1) >= next.wrapping_sub(hole) & (mask - 1) {
+                self.indices[hole as usize] = self.indices[next as usize].take();
+                hole = next;
+            }
+            next = (next + 1) & (mask - 1);
         }
+
+        self.extra_len -= entry.extra_len();
+        self.is_full = self.entries.len() as Size > self.delim;
+        let (_,value) = entry.into_parts();
+        Some(value)
     }
 
     /// Inserts a key-value pair into the map.
@@ -232,6 +241,7 @@ impl HeaderMap {
                         } else {
                             // Returns duplicate
                             let entry = replace(entry, Entry::new(hash, name, value));
+                            self.extra_len -= entry.extra_len();
                             Some(entry.into_parts().1)
                         };
                     }
@@ -252,17 +262,30 @@ impl HeaderMap {
         let new_cap = (self.indices.len() + 1).next_power_of_two().max(8);
 
         let mut me = HeaderMap::with_capacity(new_cap);
+        let mask = new_cap as Size;
 
+        // names are unique, so entries can be moved as is, including extra values
         for entry in take(&mut self.entries) {
-            let (name,value) = entry.into_parts();
-            me.try_insert(name, value, true);
+            let mut index = entry.hash() & (mask - 1);
+            while me.indices[index as usize].is_some() {
+                index = (index + 1) & (mask - 1);
+            }
+            me.indices[index as usize] = Some(me.entries.len() as Size);
+            me.entries.push(entry);
         }
 
-        println!("Resize({new_cap})");
+        me.extra_len = self.extra_len;
+        me.is_full = me.entries.len() as Size > me.delim;
         *self = me;
     }
 }
 
+impl Default for HeaderMap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl std::fmt::Debug for HeaderMap {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Headers")
@@ -274,10 +297,11 @@ impl std::fmt::Debug for HeaderMap {
 mod test {
     use super::*;
 
+    #[test]
     fn header_map() {
         let mut map = HeaderMap::new();
 
-        assert!(map.get(&HeaderName::new("content-type")).is_none());
+        assert!(map.get(HeaderName::new("content-type")).is_none());
 
         map.insert(HeaderName::new("content-type"), HeaderValue::from_string("FOO"));
         assert!(map.contains_key(&HeaderName::new("content-type")));
@@ -297,7 +321,7 @@ mod test {
         assert!(map.contains_key(&HeaderName::new("referer")));
         assert!(map.contains_key(&HeaderName::new("rim")));
 
-        println!("Insert Allocate");
+        // insert that allocate
 
         map.insert(HeaderName::new("lea"), HeaderValue::from_string("BAR"));
 
@@ -310,7 +334,7 @@ mod test {
         assert!(map.contains_key(&HeaderName::new("rim")));
         assert!(map.contains_key(&HeaderName::new("lea")));
 
-        println!("Insert Multi");
+        // insert multiple value
 
         map.append(HeaderName::new("content-length"), HeaderValue::from_string("BAR"));
 
@@ -320,12 +344,12 @@ mod test {
         assert!(map.contains_key(&HeaderName::new("referer")));
         assert!(map.contains_key(&HeaderName::new("rim")));
 
-        let mut all = map.get_all(&HeaderName::new("content-length"));
+        let mut all = map.get_all(HeaderName::new("content-length"));
         assert!(matches!(all.next(), Some(v) if matches!(v.as_str(),Ok("LEN"))));
         assert!(matches!(all.next(), Some(v) if matches!(v.as_str(),Ok("BAR"))));
         assert!(all.next().is_none());
 
-        assert!(map.remove(&HeaderName::new("accept")).is_some());
+        assert!(map.remove(HeaderName::new("accept")).is_some());
         assert!(map.contains_key(&HeaderName::new("content-type")));
         assert!(map.contains_key(&HeaderName::new("content-length")));
         assert!(map.contains_key(&HeaderName::new("host")));
@@ -334,7 +358,7 @@ mod test {
         assert!(map.contains_key(&HeaderName::new("rim")));
         assert!(map.contains_key(&HeaderName::new("lea")));
 
-        assert!(map.remove(&HeaderName::new("lea")).is_some());
+        assert!(map.remove(HeaderName::new("lea")).is_some());
         assert!(map.contains_key(&HeaderName::new("content-type")));
         assert!(map.contains_key(&HeaderName::new("content-length")));
         assert!(map.contains_key(&HeaderName::new("host")));
@@ -342,10 +366,9 @@ mod test {
         assert!(map.contains_key(&HeaderName::new("referer")));
         assert!(map.contains_key(&HeaderName::new("rim")));
 
-        assert!(map.remove(&HeaderName::new("content-length")).is_some());
-
-        dbg!(map.len());
-        dbg!(map);
+        assert!(map.remove(HeaderName::new("content-length")).is_some());
+        assert!(!map.contains_key(&HeaderName::new("content-length")));
+        assert_eq!(map.len(), 5);
     }
 }
 
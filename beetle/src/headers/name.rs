@@ -54,7 +54,7 @@ impl HeaderName {
 
 // ===== Ref Traits =====
 
-pub(crate) struct HeaderNameRef<'a> {
+pub struct HeaderNameRef<'a> {
     name: &'a str,
     hash: u16,
 }
@@ -69,12 +69,12 @@ impl<'a> HeaderNameRef<'a> {
     }
 }
 
-pub(crate) trait SealedRef: Sized {
+pub trait SealedRef: Sized {
     fn hash(&self) -> u16;
 
     fn as_str(&self) -> &str;
 
-    fn to_header_ref(&self) -> HeaderNameRef {
+    fn to_header_ref(&self) -> HeaderNameRef<'_> {
         HeaderNameRef {
             name: self.as_str(),
             hash: self.hash(),
@@ -92,7 +92,7 @@ impl<S: SealedRef> SealedRef for &S {
     }
 }
 
-impl<'a> SealedRef for &'a str {
+impl SealedRef for &str {
     fn hash(&self) -> u16 {
         hash_str(self)
     }
@@ -123,12 +123,12 @@ fn hash_str(s: &str) -> u16 {
 
 pub trait AsHeaderName: SealedRef { }
 impl AsHeaderName for HeaderName { }
-impl<'a> AsHeaderName for &'a str { }
+impl AsHeaderName for &str { }
 impl<K: AsHeaderName> AsHeaderName for &K { }
 
 // ===== Owned Traits =====
 
-pub(crate) trait Sealed: Sized {
+pub trait Sealed: Sized {
     fn into_header_name(self) -> HeaderName;
 }
 
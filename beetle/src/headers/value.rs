@@ -68,6 +68,15 @@ impl HeaderValue {
         Self::try_from_string(value).expect("failed to parse header")
     }
 
+    /// Parse [`HeaderValue`] from static str.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if header contains invalid character.
+    pub fn from_static(value: &'static str) -> HeaderValue {
+        Self::from_string(ByteStr::from_static(value))
+    }
+
     /// Returns value as slice.
     pub fn as_bytes(&self) -> &[u8] {
         match &self.repr {
@@ -97,7 +106,7 @@ impl HeaderValue {
     }
 
     /// Parse `"; "` separated value as [`Iterator`].
-    pub fn as_sequence(&self) -> Sequence {
+    pub fn as_sequence(&self) -> Sequence<'_> {
         Sequence {
             value: self.as_str().ok().map(|e| e.split("; ")),
         }
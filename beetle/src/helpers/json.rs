@@ -1,7 +1,7 @@
 use bytes::Bytes;
 use serde::{Serialize, de::DeserializeOwned};
 use std::{
-    fmt, io,
+    fmt,
     marker::PhantomData,
     pin::Pin,
     task::{
@@ -12,7 +12,8 @@ use std::{
 };
 
 use crate::{
-    FromRequest, IntoResponse, Request, Response, helpers::BadRequest, http::StatusCode, response,
+    FromRequest, IntoResponse, Request, Response, helpers::BadRequest, http::StatusCode,
+    request::BodyError, response,
 };
 
 pub struct Json<T>(pub T);
@@ -94,13 +95,13 @@ impl<T: DeserializeOwned> Future for JsonFuture<T> {
 pub enum JsonFutureError {
     /// `Content-Type` header is not `application/json`
     ContentType,
-    Io(io::Error),
+    Body(BodyError),
     Serde(serde_json::Error),
 }
 
-impl From<BadRequest<io::Error>> for JsonFutureError {
-    fn from(value: BadRequest<io::Error>) -> Self {
-        Self::Io(value.0)
+impl From<BodyError> for JsonFutureError {
+    fn from(value: BodyError) -> Self {
+        Self::Body(value)
     }
 }
 
@@ -117,7 +118,7 @@ impl fmt::Display for JsonFutureError {
         use JsonFutureError::*;
         match self {
             ContentType => f.write_str("`Content-Type` missmatch"),
-            Io(e) => write!(f, "{e}"),
+            Body(e) => write!(f, "{e}"),
             Serde(error) => write!(f, "failed to parse json: {error}"),
         }
     }
@@ -131,6 +132,9 @@ impl fmt::Debug for JsonFutureError {
 
 impl IntoResponse for JsonFutureError {
     fn into_response(self) -> Response {
-        BadRequest::new(self).into_response()
+        match self {
+            JsonFutureError::Body(error) => error.into_response(),
+            _ => BadRequest::new(self).into_response(),
+        }
     }
 }
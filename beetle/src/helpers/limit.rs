@@ -0,0 +1,187 @@
+//! request body limit
+use std::convert::Infallible;
+
+use super::Layer;
+use crate::{
+    FromRequest, IntoResponse, Request, Response,
+    futures::{EitherInto, FutureExt, MapOk, TryFutureExt},
+    http::Method,
+    service::{HttpService, Service},
+};
+
+/// limit request body length
+///
+/// request with declared `Content-Length` larger than the limit is rejected with
+/// `413 Content Too Large` before any body is read, otherwise the limit is applied to the
+/// request [`Body`][crate::request::Body] and enforced by body extractor like [`Bytes`],
+/// [`String`] and [`Json`][super::Json]
+///
+/// [`Bytes`]: bytes::Bytes
+#[derive(Debug, Clone, Copy)]
+pub struct BodyLimit {
+    limit: usize,
+}
+
+impl BodyLimit {
+    /// create new [`BodyLimit`] with limit in bytes
+    pub fn new(limit: usize) -> Self {
+        Self { limit }
+    }
+}
+
+impl<S> Layer<S> for BodyLimit {
+    type Service = BodyLimitService<S>;
+
+    fn layer(self, inner: S) -> Self::Service {
+        BodyLimitService { limit: self.limit, inner }
+    }
+}
+
+/// service returned by [`BodyLimit`] layer
+#[derive(Debug, Clone)]
+pub struct BodyLimitService<S> {
+    limit: usize,
+    inner: S,
+}
+
+type Reject = std::future::Ready<Result<Response, Infallible>>;
+
+impl<S> Service<Request> for BodyLimitService<S>
+where
+    S: HttpService,
+{
+    type Response = Response;
+    type Error = Infallible;
+    type Future = EitherInto<Reject, S::Future, Result<Response, Infallible>>;
+
+    fn call(&self, mut req: Request) -> Self::Future {
+        let body = req.body_mut();
+        body.set_limit(Some(self.limit));
+
+        if body.is_exceeded() {
+            return std::future::ready(Ok(body.reject().into_response())).left_into();
+        }
+
+        self.inner.call(req).right_into()
+    }
+
+    fn methods(&self) -> Option<&[Method]> {
+        self.inner.methods()
+    }
+}
+
+/// extractor with its own request body limit
+///
+/// [`BodyLimit`] layer rejects request with `Content-Length` larger than its limit before
+/// the handler is called, so this can only lower the effective limit, the smaller of both
+/// limit is applied to chunked body
+///
+/// ```
+/// use beetle::{Router, helpers::Limited, post};
+/// use bytes::Bytes;
+///
+/// async fn upload(Limited(body): Limited<Bytes, { 1024 * 1024 }>) -> String {
+///     format!("received {} bytes", body.len())
+/// }
+///
+/// let router = Router::new().route("/upload", post(upload));
+/// ```
+#[derive(Debug)]
+pub struct Limited<T, const LIMIT: usize>(pub T);
+
+impl<T, const LIMIT: usize> FromRequest for Limited<T, LIMIT>
+where
+    T: FromRequest,
+{
+    type Error = T::Error;
+    type Future = MapOk<T::Future, fn(T) -> Self>;
+
+    fn from_request(mut req: Request) -> Self::Future {
+        let body = req.body_mut();
+        body.set_limit(Some(body.limit().map_or(LIMIT, |limit| limit.min(LIMIT))));
+        T::from_request(req).map_ok(Limited as _)
+    }
+}
+
+#[cfg(all(test, feature = "tokio"))]
+mod test {
+    use bytes::Bytes;
+    use std::sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    };
+
+    use super::*;
+    use crate::{
+        Router, post,
+        service::{BoxCloneService, tcp::Config},
+        testing::serve,
+    };
+
+    fn status_line(output: &[u8]) -> String {
+        let output = String::from_utf8_lossy(output);
+        output.lines().next().unwrap_or_default().to_owned()
+    }
+
+    #[test]
+    fn content_length_exceeded() {
+        let called = Arc::new(AtomicBool::new(false));
+        let handler = {
+            let called = called.clone();
+            move |_: Bytes| {
+                called.store(true, Ordering::Relaxed);
+                async { String::new() }
+            }
+        };
+        let router = Router::new().route("/", post(handler)).layer(BodyLimit::new(4));
+
+        // the body is never sent, the response must not wait for it
+        let input = b"POST / HTTP/1.1\r\nhost: a\r\ncontent-length: 1024\r\n\r\n";
+        let output = serve(BoxCloneService::new(router), Config::default(), input);
+        assert_eq!(status_line(&output), "HTTP/1.1 413 Content Too Large");
+        assert!(!called.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn chunked_exceeded() {
+        let router = Router::new()
+            .route("/", post(|body: Bytes| async move { body.len().to_string() }))
+            .layer(BodyLimit::new(4));
+
+        let input = b"POST / HTTP/1.1\r\nhost: a\r\ntransfer-encoding: chunked\r\n\r\n\
+            3\r\nabc\r\n3\r\ndef\r\n0\r\n\r\n";
+        let output = serve(BoxCloneService::new(router), Config::default(), input);
+        assert_eq!(status_line(&output), "HTTP/1.1 413 Content Too Large");
+
+        // within the limit
+        let input = b"POST / HTTP/1.1\r\nhost: a\r\ntransfer-encoding: chunked\r\n\
+            connection: close\r\n\r\n3\r\nabc\r\n0\r\n\r\n";
+        let router = Router::new()
+            .route("/", post(|body: Bytes| async move { body.len().to_string() }))
+            .layer(BodyLimit::new(4));
+        let output = serve(BoxCloneService::new(router), Config::default(), input);
+        assert_eq!(status_line(&output), "HTTP/1.1 200 OK");
+        assert!(output.ends_with(b"3"));
+    }
+
+    #[test]
+    fn limited() {
+        let router = Router::new().route(
+            "/",
+            post(|Limited(body): Limited<Bytes, 4>| async move { body.len().to_string() }),
+        );
+
+        let input = b"POST / HTTP/1.1\r\nhost: a\r\ncontent-length: 10\r\n\r\n0123456789";
+        let output = serve(BoxCloneService::new(router), Config::default(), input);
+        assert_eq!(status_line(&output), "HTTP/1.1 413 Content Too Large");
+
+        // larger limit does not raise the layer limit
+        let router = Router::new()
+            .route("/", post(|Limited(body): Limited<Bytes, 1024>| async move { body.len().to_string() }))
+            .layer(BodyLimit::new(4));
+        let input = b"POST / HTTP/1.1\r\nhost: a\r\ntransfer-encoding: chunked\r\n\r\n\
+            3\r\nabc\r\n3\r\ndef\r\n0\r\n\r\n";
+        let output = serve(BoxCloneService::new(router), Config::default(), input);
+        assert_eq!(status_line(&output), "HTTP/1.1 413 Content Too Large");
+    }
+}
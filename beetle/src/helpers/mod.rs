@@ -1,12 +1,28 @@
 //! helper types and traits
 
+pub mod limit;
+pub mod range;
+
 #[cfg(feature = "json")]
 pub mod json;
 
+#[cfg(feature = "serde")]
+pub mod query;
+
+#[doc(inline)]
+pub use limit::{BodyLimit, Limited};
+
+#[doc(inline)]
+pub use range::{Range, Ranged};
+
 #[cfg(feature = "json")]
 #[doc(inline)]
 pub use json::Json;
 
+#[cfg(feature = "serde")]
+#[doc(inline)]
+pub use query::Query;
+
 /// service which holds another service
 pub trait Layer<S> {
     type Service;
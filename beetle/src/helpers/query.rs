@@ -0,0 +1,79 @@
+use serde::de::DeserializeOwned;
+use std::future::{Ready, ready};
+
+use crate::{
+    FromRequestParts,
+    helpers::BadRequest,
+    request::Parts,
+};
+
+/// Extract and deserialize the request query string.
+///
+/// Missing query string is treated as an empty one.
+///
+/// This requires `serde` features to be enabled.
+#[derive(Debug)]
+pub struct Query<T>(pub T);
+
+impl<T: DeserializeOwned> FromRequestParts for Query<T> {
+    type Error = BadRequest<serde_urlencoded::de::Error>;
+
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request_parts(parts: &mut Parts) -> Self::Future {
+        ready(
+            serde_urlencoded::from_str(parts.query().unwrap_or_default())
+                .map(Query)
+                .map_err(BadRequest),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::{Router, get, http::Method, testing::{body, call, request}};
+
+    fn query(uri: &'static str) -> (u16, String) {
+        async fn pairs(Query(pairs): Query<Vec<(String, String)>>) -> String {
+            format!("{pairs:?}")
+        }
+        async fn page(Query(query): Query<HashMap<String, u32>>) -> String {
+            format!("{:?}", query.get("page"))
+        }
+
+        let router = Router::new()
+            .route("/pairs", get(pairs))
+            .route("/page", get(page));
+        let response = call(&router, request(Method::GET, uri, &[]));
+        let status = response.status().status();
+        (status, String::from_utf8(body(response).into()).unwrap())
+    }
+
+    #[test]
+    fn missing_query() {
+        assert_eq!(query("/pairs"), (200, "[]".into()));
+        assert_eq!(query("/pairs?"), (200, "[]".into()));
+        assert_eq!(query("/page"), (200, "None".into()));
+    }
+
+    #[test]
+    fn percent_decoded() {
+        assert_eq!(query("/pairs?name=a%20b+c&k%26=v%3D"), (200, r#"[("name", "a b c"), ("k&", "v=")]"#.into()));
+    }
+
+    #[test]
+    fn repeated_key() {
+        assert_eq!(query("/pairs?tag=a&tag=b"), (200, r#"[("tag", "a"), ("tag", "b")]"#.into()));
+        // map keep the last value
+        assert_eq!(query("/page?page=1&page=2"), (200, "Some(2)".into()));
+    }
+
+    #[test]
+    fn deserialize_error() {
+        assert_eq!(query("/page?page=one").0, 400);
+        assert_eq!(query("/page?page=-1").0, 400);
+    }
+}
@@ -0,0 +1,460 @@
+//! byte range request
+use bytes::{Bytes, BytesMut};
+use futures_core::Stream;
+use std::{
+    collections::VecDeque,
+    convert::Infallible,
+    future::{Ready, ready},
+    hash::{BuildHasher, Hasher, RandomState},
+    io, ops,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use crate::{
+    common::ByteStr,
+    headers::{HeaderMap, HeaderValue},
+    http::{Method, StatusCode, date::parse_http_date},
+    request::{FromRequestParts, Parts},
+    response::{Body, IntoResponse, Response},
+};
+
+/// maximum number of ranges after coalescing, more ranges is ignored and the full content is
+/// served
+const MAX_RANGES: usize = 64;
+
+/// `Range` request header, with its `If-Range` precondition
+///
+/// as an extractor, it never fails, missing or invalid `Range` header, or request method other
+/// than `GET`, result in empty range, which does not alter the response
+///
+/// use [`Ranged`] to respond with partial content
+///
+/// ```
+/// use beetle::helpers::{Range, Ranged};
+///
+/// async fn download(range: Range) -> Ranged<String> {
+///     Ranged(range, "0123456789".repeat(1000))
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Range {
+    specs: Vec<Spec>,
+    if_range: Option<ByteStr>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Spec {
+    /// `first-last`
+    Bounded(u64, u64),
+    /// `first-`
+    From(u64),
+    /// `-suffix`
+    Suffix(u64),
+}
+
+impl Range {
+    /// parse `Range` header value, e.g: `bytes=0-99, 200-`
+    ///
+    /// returns [`None`] if the value is invalid or the unit is not `bytes`
+    pub fn parse(value: &str) -> Option<Self> {
+        let (unit, set) = value.split_once('=')?;
+        if !unit.trim().eq_ignore_ascii_case("bytes") {
+            return None;
+        }
+
+        let mut specs = Vec::new();
+        for spec in set.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (first, last) = spec.split_once('-')?;
+            let spec = match (first, last) {
+                ("", suffix) => Spec::Suffix(parse_int(suffix)?),
+                (first, "") => Spec::From(parse_int(first)?),
+                (first, last) => {
+                    let (first, last) = (parse_int(first)?, parse_int(last)?);
+                    if first > last {
+                        return None;
+                    }
+                    Spec::Bounded(first, last)
+                }
+            };
+            specs.push(spec);
+        }
+
+        match specs.is_empty() {
+            true => None,
+            false => Some(Self { specs, if_range: None }),
+        }
+    }
+
+    /// read `Range` and `If-Range` from request headers
+    ///
+    /// returns empty range if `Range` is missing or invalid
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let range = headers.get("range").and_then(|e| e.as_str().ok()).and_then(Self::parse);
+        let Some(mut range) = range else {
+            return Self::default();
+        };
+        if let Some(if_range) = headers.get("if-range") {
+            // precondition that cannot be evaluated never match
+            let Ok(if_range) = if_range.as_str() else {
+                return Self::default();
+            };
+            range.if_range = Some(ByteStr::copy_from_str(if_range.trim()));
+        }
+        range
+    }
+
+    /// returns `true` if no range is requested
+    pub fn is_empty(&self) -> bool {
+        self.specs.is_empty()
+    }
+
+    /// returns the satisfiable ranges for content with given length
+    ///
+    /// ranges are sorted, and overlapping or adjacent ranges are coalesced, returns [`None`] if
+    /// none of the range is satisfiable
+    pub fn ranges(&self, len: u64) -> Option<Vec<ops::Range<u64>>> {
+        let mut ranges = self
+            .specs
+            .iter()
+            .filter_map(|spec| {
+                let range = match *spec {
+                    Spec::Bounded(first, last) => first..last.saturating_add(1).min(len),
+                    Spec::From(first) => first..len,
+                    Spec::Suffix(suffix) => len.saturating_sub(suffix)..len,
+                };
+                (range.start < range.end).then_some(range)
+            })
+            .collect::<Vec<_>>();
+
+        ranges.sort_unstable_by_key(|e| e.start);
+        let mut coalesced: Vec<ops::Range<u64>> = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            match coalesced.last_mut() {
+                Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+                _ => coalesced.push(range),
+            }
+        }
+
+        match coalesced.is_empty() {
+            true => None,
+            false => Some(coalesced),
+        }
+    }
+
+    /// returns `true` if the range should be applied to response with given headers
+    ///
+    /// `If-Range` entity tag is matched with strong comparison against `ETag`, and date is
+    /// matched exactly against `Last-Modified`
+    pub(crate) fn is_fresh(&self, headers: &HeaderMap) -> bool {
+        let Some(if_range) = self.if_range.as_deref() else {
+            return true;
+        };
+        if if_range.starts_with('"') {
+            return headers.get("etag").is_some_and(|etag| etag.as_bytes() == if_range.as_bytes());
+        }
+        let modified = headers.get("last-modified").and_then(|e| e.as_str().ok()).and_then(parse_http_date);
+        modified.is_some_and(|modified| parse_http_date(if_range) == Some(modified))
+    }
+
+    /// apply the range to response
+    ///
+    /// the range is only applied to `200 OK` response with known body length, a single range
+    /// result in `206 Partial Content`, multiple ranges result in `206 Partial Content` with
+    /// `multipart/byteranges` body, and unsatisfiable range result in
+    /// `416 Range Not Satisfiable`
+    ///
+    /// `Accept-Ranges: bytes` is added to response with known body length
+    pub fn respond(&self, res: Response) -> Response {
+        if res.status() != StatusCode::OK || res.headers().get("content-range").is_some() {
+            return res;
+        }
+        let Some(len) = res.body().content_len() else {
+            return res;
+        };
+        let (mut parts, body) = res.into_parts();
+        parts.headers_mut().insert("accept-ranges", HeaderValue::from_static("bytes"));
+
+        let len = len as u64;
+        if self.is_empty() || !self.is_fresh(parts.headers()) {
+            return Response::from_parts(parts, body);
+        }
+
+        let Some(ranges) = self.ranges(len) else {
+            let headers = parts.headers_mut();
+            headers.remove("content-length");
+            headers.insert("content-range", HeaderValue::from_string(format!("bytes */{len}")));
+            *parts.status_mut() = StatusCode::RANGE_NOT_SATISFIABLE;
+            return Response::from_parts(parts, Body::empty());
+        };
+        if ranges.len() > MAX_RANGES {
+            return Response::from_parts(parts, body);
+        }
+
+        *parts.status_mut() = StatusCode::PARTIAL_CONTENT;
+        let headers = parts.headers_mut();
+        headers.remove("content-length");
+
+        let segments = match <[_; 1]>::try_from(ranges) {
+            Ok([range]) => {
+                let content_range = format!("bytes {}-{}/{len}", range.start, range.end - 1);
+                headers.insert("content-range", HeaderValue::from_string(content_range));
+                vec![Segment::Slice(range)]
+            }
+            Err(ranges) => {
+                let boundary = format!("{:016x}", RandomState::new().build_hasher().finish());
+                let content_type = headers.remove("content-type");
+                headers.insert(
+                    "content-type",
+                    HeaderValue::from_string(format!("multipart/byteranges; boundary={boundary}")),
+                );
+                multipart(&ranges, len, &boundary, content_type.as_ref())
+            }
+        };
+
+        Response::from_parts(parts, slice(body, segments))
+    }
+}
+
+impl FromRequestParts for Range {
+    type Error = Infallible;
+
+    type Future = Ready<Result<Self, Infallible>>;
+
+    fn from_request_parts(parts: &mut Parts) -> Self::Future {
+        ready(Ok(match parts.method() {
+            Method::GET => Self::from_headers(parts.headers()),
+            _ => Self::default(),
+        }))
+    }
+}
+
+/// parse non negative integer, sign is not allowed
+fn parse_int(value: &str) -> Option<u64> {
+    match !value.is_empty() && value.bytes().all(|b| b.is_ascii_digit()) {
+        true => value.parse().ok(),
+        false => None,
+    }
+}
+
+// ===== Ranged =====
+
+/// respond with partial content according to [`Range`]
+///
+/// see [`Range::respond`] for when the range is applied
+#[derive(Debug)]
+pub struct Ranged<T>(pub Range, pub T);
+
+impl<T: IntoResponse> IntoResponse for Ranged<T> {
+    fn into_response(self) -> Response {
+        self.0.respond(self.1.into_response())
+    }
+}
+
+// ===== Body =====
+
+/// part of the partial content body
+enum Segment {
+    Literal(Bytes),
+    Slice(ops::Range<u64>),
+}
+
+impl Segment {
+    fn len(&self) -> u64 {
+        match self {
+            Segment::Literal(bytes) => bytes.len() as u64,
+            Segment::Slice(range) => range.end - range.start,
+        }
+    }
+}
+
+/// build `multipart/byteranges` body segments
+fn multipart(
+    ranges: &[ops::Range<u64>],
+    len: u64,
+    boundary: &str,
+    content_type: Option<&HeaderValue>,
+) -> Vec<Segment> {
+    let mut segments = Vec::with_capacity(ranges.len() * 2 + 1);
+    for (i, range) in ranges.iter().enumerate() {
+        let mut head = Vec::new();
+        if i != 0 {
+            head.extend_from_slice(b"\r\n");
+        }
+        head.extend_from_slice(format!("--{boundary}\r\n").as_bytes());
+        if let Some(content_type) = content_type {
+            head.extend_from_slice(b"content-type: ");
+            head.extend_from_slice(content_type.as_bytes());
+            head.extend_from_slice(b"\r\n");
+        }
+        let content_range = format!("content-range: bytes {}-{}/{len}\r\n\r\n", range.start, range.end - 1);
+        head.extend_from_slice(content_range.as_bytes());
+        segments.push(Segment::Literal(head.into()));
+        segments.push(Segment::Slice(range.clone()));
+    }
+    segments.push(Segment::Literal(format!("\r\n--{boundary}--\r\n").into()));
+    segments
+}
+
+/// build body from segments, slices must be in ascending order
+fn slice(body: Body, segments: Vec<Segment>) -> Body {
+    let len = segments.iter().map(Segment::len).sum::<u64>() as usize;
+    match body.try_into_bytes() {
+        Ok(bytes) => match &segments[..] {
+            [Segment::Slice(range)] => Body::bytes(bytes.slice(range.start as usize..range.end as usize)),
+            _ => {
+                let mut buf = BytesMut::with_capacity(len);
+                for segment in segments {
+                    match segment {
+                        Segment::Literal(literal) => buf.extend_from_slice(&literal),
+                        Segment::Slice(range) => {
+                            buf.extend_from_slice(&bytes[range.start as usize..range.end as usize])
+                        }
+                    }
+                }
+                Body::bytes(buf.freeze())
+            }
+        },
+        Err(body) => Body::sized_stream(
+            len,
+            SliceStream { body, pos: 0, chunk: Bytes::new(), segments: segments.into() },
+        ),
+    }
+}
+
+/// slice streaming body, bytes outside the slices are discarded
+struct SliceStream {
+    body: Body,
+    /// offset of `chunk` in the body
+    pos: u64,
+    chunk: Bytes,
+    segments: VecDeque<Segment>,
+}
+
+impl Stream for SliceStream {
+    type Item = io::Result<Bytes>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let me = self.get_mut();
+        loop {
+            let range = match me.segments.front_mut() {
+                None => return Poll::Ready(None),
+                Some(Segment::Literal(literal)) => {
+                    let literal = std::mem::take(literal);
+                    me.segments.pop_front();
+                    return Poll::Ready(Some(Ok(literal)));
+                }
+                Some(Segment::Slice(range)) => range.clone(),
+            };
+
+            if me.chunk.is_empty() {
+                match std::task::ready!(me.body.poll_data(cx)) {
+                    Some(Ok(chunk)) => me.chunk = chunk,
+                    Some(Err(err)) => {
+                        me.segments.clear();
+                        return Poll::Ready(Some(Err(err)));
+                    }
+                    None => {
+                        me.segments.clear();
+                        return Poll::Ready(Some(Err(io::ErrorKind::UnexpectedEof.into())));
+                    }
+                }
+                continue;
+            }
+
+            if me.pos < range.start {
+                let skip = (range.start - me.pos).min(me.chunk.len() as u64);
+                let _ = me.chunk.split_to(skip as usize);
+                me.pos += skip;
+                continue;
+            }
+
+            let take = (range.end - me.pos).min(me.chunk.len() as u64);
+            let data = me.chunk.split_to(take as usize);
+            me.pos += take;
+            if me.pos == range.end {
+                me.segments.pop_front();
+            }
+            return Poll::Ready(Some(Ok(data)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let ranges = |value: &str, len| {
+            let ranges = Range::parse(value)?.ranges(len)?;
+            Some(ranges.into_iter().map(|e| (e.start, e.end)).collect::<Vec<_>>())
+        };
+
+        assert_eq!(ranges("bytes=0-99", 1000), Some(vec![(0, 100)]));
+        assert_eq!(ranges("bytes=500-", 1000), Some(vec![(500, 1000)]));
+        assert_eq!(ranges("bytes=-100", 1000), Some(vec![(900, 1000)]));
+        assert_eq!(ranges("bytes=900-2000", 1000), Some(vec![(900, 1000)]));
+        assert_eq!(ranges("bytes=-2000", 1000), Some(vec![(0, 1000)]));
+        assert_eq!(ranges("Bytes = 0-0, -1", 1000), Some(vec![(0, 1), (999, 1000)]));
+        assert_eq!(ranges("bytes=500-599, 0-99", 1000), Some(vec![(0, 100), (500, 600)]));
+        assert_eq!(ranges("bytes=0-99, 50-149, 150-199", 1000), Some(vec![(0, 200)]));
+        assert_eq!(ranges("bytes=0-1, 5000-", 1000), Some(vec![(0, 2)]));
+
+        assert_eq!(ranges("bytes=1000-", 1000), None);
+        assert_eq!(ranges("bytes=-0", 1000), None);
+        assert_eq!(ranges("bytes=0-", 0), None);
+
+        assert_eq!(Range::parse("bytes=5-1"), None);
+        assert_eq!(Range::parse("bytes=+1-2"), None);
+        assert_eq!(Range::parse("bytes=a-b"), None);
+        assert_eq!(Range::parse("bytes=1"), None);
+        assert_eq!(Range::parse("bytes="), None);
+        assert_eq!(Range::parse("items=0-1"), None);
+    }
+
+    #[test]
+    fn if_range() {
+        let mut req = HeaderMap::new();
+        req.insert("range", HeaderValue::from_static("bytes=0-1"));
+        req.insert("if-range", HeaderValue::from_static("\"v1\""));
+        let range = Range::from_headers(&req);
+
+        let mut res = HeaderMap::new();
+        res.insert("etag", HeaderValue::from_static("\"v1\""));
+        assert!(range.is_fresh(&res));
+        res.insert("etag", HeaderValue::from_static("\"v2\""));
+        assert!(!range.is_fresh(&res));
+        res.insert("etag", HeaderValue::from_static("W/\"v1\""));
+        assert!(!range.is_fresh(&res));
+
+        req.insert("if-range", HeaderValue::from_static("Sun, 06 Nov 1994 08:49:37 GMT"));
+        let range = Range::from_headers(&req);
+        let mut res = HeaderMap::new();
+        res.insert("last-modified", HeaderValue::from_static("Sun, 06 Nov 1994 08:49:37 GMT"));
+        assert!(range.is_fresh(&res));
+        res.insert("last-modified", HeaderValue::from_static("Mon, 07 Nov 1994 08:49:37 GMT"));
+        assert!(!range.is_fresh(&res));
+    }
+
+    #[test]
+    fn multipart_body() {
+        let range = Range::parse("bytes=0-1, 4-5").unwrap();
+        let mut res = Response::new(Body::bytes("0123456789"));
+        res.headers_mut().insert("content-type", HeaderValue::from_static("text/plain"));
+
+        let res = range.respond(res);
+        assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT);
+        let content_type = res.headers().get("content-type").unwrap().as_str().unwrap();
+        let boundary = content_type.strip_prefix("multipart/byteranges; boundary=").unwrap().to_owned();
+        let body = res.into_body().try_into_bytes().ok().unwrap();
+        assert_eq!(
+            body,
+            format!(
+                "--{boundary}\r\ncontent-type: text/plain\r\ncontent-range: bytes 0-1/10\r\n\r\n01\
+                \r\n--{boundary}\r\ncontent-type: text/plain\r\ncontent-range: bytes 4-5/10\r\n\r\n45\
+                \r\n--{boundary}--\r\n"
+            )
+        );
+    }
+}
@@ -0,0 +1,130 @@
+//! HTTP date, e.g: `Sun, 06 Nov 1994 08:49:37 GMT`.
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const DAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+/// Format time as IMF-fixdate, time before unix epoch is formatted as the epoch.
+pub(crate) fn fmt_http_date(time: SystemTime) -> String {
+    let secs = time.duration_since(UNIX_EPOCH).map(|e| e.as_secs()).unwrap_or(0);
+    let days = secs / 86400;
+    let rem = secs % 86400;
+    let (year, month, day) = civil_from_days(days as i64);
+    format!(
+        "{}, {day:02} {} {year:04} {:02}:{:02}:{:02} GMT",
+        DAYS[(days % 7) as usize],
+        MONTHS[month as usize - 1],
+        rem / 3600,
+        rem / 60 % 60,
+        rem % 60,
+    )
+}
+
+/// Parse IMF-fixdate, or the obsolete rfc850 and asctime formats.
+///
+/// Two digit year of rfc850 is interpreted as 1970 to 2069.
+pub(crate) fn parse_http_date(value: &str) -> Option<SystemTime> {
+    let value = value.trim().as_bytes();
+    let (year, month, day, time) = match value {
+        // Sun, 06 Nov 1994 08:49:37 GMT
+        [
+            _, _, _, b',', b' ', d1, d2, b' ', m1, m2, m3, b' ', y1, y2, y3, y4, b' ',
+            time @ .., b' ', b'G', b'M', b'T',
+        ] => {
+            (num(&[*y1, *y2, *y3, *y4])?, [*m1, *m2, *m3], num(&[*d1, *d2])?, time)
+        }
+        // Sun Nov  6 08:49:37 1994
+        [_, _, _, b' ', m1, m2, m3, b' ', d1, d2, b' ', time @ .., b' ', y1, y2, y3, y4] => {
+            let day = match d1 {
+                b' ' => num(&[*d2])?,
+                _ => num(&[*d1, *d2])?,
+            };
+            (num(&[*y1, *y2, *y3, *y4])?, [*m1, *m2, *m3], day, time)
+        }
+        // Sunday, 06-Nov-94 08:49:37 GMT
+        _ => {
+            let (_, rest) = value.split_at(value.iter().position(|&b| b == b',')?);
+            let [
+                b',', b' ', d1, d2, b'-', m1, m2, m3, b'-', y1, y2, b' ',
+                time @ .., b' ', b'G', b'M', b'T',
+            ] = rest
+            else {
+                return None;
+            };
+            let year = match num(&[*y1, *y2])? {
+                year @ 70.. => 1900 + year,
+                year => 2000 + year,
+            };
+            (year, [*m1, *m2, *m3], num(&[*d1, *d2])?, time)
+        }
+    };
+
+    let [h1, h2, b':', n1, n2, b':', s1, s2] = *time else {
+        return None;
+    };
+    let month = MONTHS.iter().position(|e| e.as_bytes() == month)? as u64 + 1;
+    let (hour, min, sec) = (num(&[h1, h2])?, num(&[n1, n2])?, num(&[s1, s2])?);
+    if year < 1970 || !(1..=31).contains(&day) || hour > 23 || min > 59 || sec > 60 {
+        return None;
+    }
+
+    let days = days_from_civil(year as i64, month as u32, day as u32);
+    let secs = days as u64 * 86400 + hour * 3600 + min * 60 + sec;
+    Some(UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+fn num(digits: &[u8]) -> Option<u64> {
+    digits.iter().try_fold(0, |acc, b| b.is_ascii_digit().then(|| acc * 10 + (b - b'0') as u64))
+}
+
+// http://howardhinnant.github.io/date_algorithms.html
+
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + (month <= 2) as i64;
+    (year, month, day)
+}
+
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = year - (month <= 2) as i64;
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = (month as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn http_date() {
+        let time = UNIX_EPOCH + Duration::from_secs(784111777);
+        assert_eq!(fmt_http_date(time), "Sun, 06 Nov 1994 08:49:37 GMT");
+        assert_eq!(parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT"), Some(time));
+
+        assert_eq!(fmt_http_date(UNIX_EPOCH), "Thu, 01 Jan 1970 00:00:00 GMT");
+        let leap = UNIX_EPOCH + Duration::from_secs(1709164800);
+        assert_eq!(fmt_http_date(leap), "Thu, 29 Feb 2024 00:00:00 GMT");
+        assert_eq!(parse_http_date(&fmt_http_date(leap)), Some(leap));
+
+        // obsolete formats
+        assert_eq!(parse_http_date("Sunday, 06-Nov-94 08:49:37 GMT"), Some(time));
+        assert_eq!(parse_http_date("Sun Nov  6 08:49:37 1994"), Some(time));
+        assert_eq!(parse_http_date("Thursday, 29-Feb-24 00:00:00 GMT"), Some(leap));
+        assert_eq!(parse_http_date("Thu Feb 29 00:00:00 2024"), Some(leap));
+        assert_eq!(parse_http_date("Sunday, 06-Nov-1994 08:49:37 GMT"), None);
+        assert_eq!(parse_http_date("Sun Nov 6 08:49:37 1994"), None);
+        assert_eq!(parse_http_date("Sun, 06 Foo 1994 08:49:37 GMT"), None);
+        assert_eq!(parse_http_date("Sun, 06 Nov 1994 25:49:37 GMT"), None);
+    }
+}
@@ -21,6 +21,12 @@ impl Extensions {
         Some(unsafe { ok.downcast_ref::<T>().unwrap_unchecked() })
     }
 
+    pub fn get_mut<T: Any>(&mut self) -> Option<&mut T> {
+        let ok = self.0.get_mut(&TypeId::of::<T>())?;
+        // SAFETY: the provided T is equal to the value
+        Some(unsafe { ok.downcast_mut::<T>().unwrap_unchecked() })
+    }
+
     pub fn insert<T: Any + Send + Sync>(&mut self, value: T) -> Option<T> {
         let ok = self.0.insert(TypeId::of::<T>(), Box::new(value))?;
         // SAFETY: the provided T is equal to the value
@@ -1,6 +1,11 @@
+use crate::common::ByteStr;
 
 /// HTTP Method.
-#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+///
+/// Method is case-sensitive, `get` is an extension method that is different from [`GET`].
+///
+/// [`GET`]: Method::GET
+#[derive(Clone, Default, Debug, PartialEq, Eq, Hash)]
 pub enum Method {
     #[default]
     GET,
@@ -10,11 +15,38 @@ pub enum Method {
     DELETE,
     HEAD,
     CONNECT,
+    OPTIONS,
+    TRACE,
+    /// Extension method.
+    Extension(ByteStr),
 }
 
 impl Method {
+    /// Parse method from bytes.
+    ///
+    /// Bytes that is not one of the standard methods is parsed as [`Method::Extension`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Method, InvalidMethod> {
+        Ok(match bytes {
+            b"GET" => Method::GET,
+            b"POST" => Method::POST,
+            b"PUT" => Method::PUT,
+            b"PATCH" => Method::PATCH,
+            b"DELETE" => Method::DELETE,
+            b"HEAD" => Method::HEAD,
+            b"CONNECT" => Method::CONNECT,
+            b"OPTIONS" => Method::OPTIONS,
+            b"TRACE" => Method::TRACE,
+            _ if !bytes.is_empty() && bytes.iter().copied().all(is_tchar) => {
+                // SAFETY: token is ascii
+                let ext = unsafe { std::str::from_utf8_unchecked(bytes) };
+                Method::Extension(ByteStr::copy_from_str(ext))
+            }
+            _ => return Err(InvalidMethod { _p: () }),
+        })
+    }
+
     /// Returns the string representation.
-    pub const fn as_str(&self) -> &'static str {
+    pub fn as_str(&self) -> &str {
         match self {
             Method::GET => "GET",
             Method::POST => "POST",
@@ -23,13 +55,41 @@ impl Method {
             Method::DELETE => "DELETE",
             Method::HEAD => "HEAD",
             Method::CONNECT => "CONNECT",
+            Method::OPTIONS => "OPTIONS",
+            Method::TRACE => "TRACE",
+            Method::Extension(ext) => ext.as_str(),
         }
     }
 }
 
+/// `tchar` as defined in RFC 9110 section 5.6.2
+pub(crate) fn is_tchar(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b)
+}
+
 impl std::fmt::Display for Method {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_str(self.as_str())
     }
 }
 
+// ===== Error =====
+
+/// Method is not a valid token.
+pub struct InvalidMethod {
+    _p: (),
+}
+
+impl std::error::Error for InvalidMethod { }
+
+impl std::fmt::Display for InvalidMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("invalid method")
+    }
+}
+
+impl std::fmt::Debug for InvalidMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("InvalidMethod").finish()
+    }
+}
@@ -1,10 +1,17 @@
 //! HTTP Protocol.
 mod method;
+mod uri;
 mod version;
 mod status;
 mod extension;
+// formatting is only used by `ServeDir`
+#[cfg_attr(not(feature = "tokio"), allow(dead_code))]
+pub(crate) mod date;
 
-pub use method::Method;
+pub use method::{Method, InvalidMethod};
+pub(crate) use method::is_tchar;
+pub use uri::{Uri, InvalidUri};
+pub(crate) use uri::percent_decode;
 pub use version::Version;
 pub use status::StatusCode;
 pub use extension::Extensions;
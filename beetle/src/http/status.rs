@@ -96,6 +96,12 @@ status_code_v3! {
     201 CREATED "Created";
     /// There is no content to send for this request, but the headers are useful.
     204 NO_CONTENT "No Content";
+    /// This response code is used in response to a range request when the client has requested a
+    /// part or parts of a resource.
+    206 PARTIAL_CONTENT "Partial Content";
+    /// The URL of the requested resource has been changed permanently. The new URL is given in the
+    /// response.
+    301 MOVED_PERMANENTLY "Moved Permanently";
     /// This response code means that the URI of requested resource has been changed temporarily.
     302 FOUND "Found";
     /// The server sent this response to direct the client to get the requested resource at another
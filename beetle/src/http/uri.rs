@@ -0,0 +1,384 @@
+use std::borrow::Cow;
+
+use crate::common::ByteStr;
+
+/// HTTP Request Target.
+///
+/// Supports all four request target forms:
+///
+/// - origin-form, `/users?id=1`
+/// - absolute-form, `http://example.com/users?id=1`
+/// - authority-form, `example.com:443`, used by `CONNECT` request
+/// - asterisk-form, `*`, used by server wide `OPTIONS` request
+///
+/// All components are slices of the same buffer, no copy is done when parsing.
+#[derive(Clone)]
+pub struct Uri {
+    value: ByteStr,
+    scheme: Option<ByteStr>,
+    authority: Option<ByteStr>,
+    path: ByteStr,
+    query: Option<ByteStr>,
+}
+
+impl Uri {
+    /// Create [`Uri`] with `/` path.
+    pub const fn new() -> Self {
+        Self {
+            value: ByteStr::from_static("/"),
+            scheme: None,
+            authority: None,
+            path: ByteStr::from_static("/"),
+            query: None,
+        }
+    }
+
+    /// Parse [`Uri`] from a static str.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the uri is invalid.
+    pub fn from_static(value: &'static str) -> Self {
+        Self::try_from_shared(ByteStr::from_static(value)).expect("invalid uri")
+    }
+
+    /// Parse [`Uri`] by copying from str.
+    pub fn try_copy_from_str(value: &str) -> Result<Self, InvalidUri> {
+        Self::try_from_shared(ByteStr::copy_from_str(value))
+    }
+
+    /// Parse [`Uri`] from [`ByteStr`] without copying.
+    pub fn try_from_shared(value: ByteStr) -> Result<Self, InvalidUri> {
+        if value.is_empty() {
+            return Err(InvalidUri::Empty);
+        }
+
+        if !value.bytes().all(|b| b.is_ascii_graphic()) {
+            return Err(InvalidUri::InvalidChar);
+        }
+
+        if value == "*" {
+            return Ok(Self {
+                path: value.clone(),
+                value,
+                scheme: None,
+                authority: None,
+                query: None,
+            });
+        }
+
+        if value.starts_with('/') {
+            let (path, query) = split_query(&value)?;
+            return Ok(Self {
+                path,
+                query,
+                value,
+                scheme: None,
+                authority: None,
+            });
+        }
+
+        if let Some((scheme, rest)) = value.split_once("://") {
+            if !is_scheme(scheme) {
+                return Err(InvalidUri::InvalidScheme);
+            }
+
+            let end = rest.find(['/', '?']).unwrap_or(rest.len());
+            let authority = &rest[..end];
+            if authority.is_empty() {
+                return Err(InvalidUri::InvalidAuthority);
+            }
+
+            let (path, query) = match &rest[end..] {
+                "" => (ByteStr::from_static("/"), None),
+                path if path.starts_with('?') => {
+                    let (_, query) = split_query(&value.slice_ref(path))?;
+                    (ByteStr::from_static("/"), query)
+                }
+                path => split_query(&value.slice_ref(path))?,
+            };
+
+            return Ok(Self {
+                scheme: Some(value.slice_ref(scheme)),
+                authority: Some(value.slice_ref(authority)),
+                path,
+                query,
+                value,
+            });
+        }
+
+        if value.contains(['/', '?', '#']) {
+            return Err(InvalidUri::InvalidAuthority);
+        }
+
+        Ok(Self {
+            authority: Some(value.clone()),
+            value,
+            scheme: None,
+            path: ByteStr::new(),
+            query: None,
+        })
+    }
+
+    /// Returns the scheme, e.g: `"http"`, only present in absolute-form.
+    pub fn scheme(&self) -> Option<&str> {
+        self.scheme.as_deref()
+    }
+
+    /// Returns the authority, e.g: `"example.com:443"`, present in absolute-form and
+    /// authority-form.
+    pub fn authority(&self) -> Option<&str> {
+        self.authority.as_deref()
+    }
+
+    /// Returns the path.
+    ///
+    /// This is empty for authority-form and `"*"` for asterisk-form.
+    pub fn path(&self) -> &ByteStr {
+        &self.path
+    }
+
+    /// Returns the query string, without the leading `?`.
+    pub fn query(&self) -> Option<&str> {
+        self.query.as_deref()
+    }
+
+    /// Returns `true` if this is an asterisk-form, `*`.
+    pub fn is_asterisk(&self) -> bool {
+        self.path == "*"
+    }
+
+    /// Returns `true` if this is an authority-form, e.g: `example.com:443`.
+    pub fn is_authority_form(&self) -> bool {
+        self.scheme.is_none() && self.authority.is_some()
+    }
+
+    /// Returns the percent decoded path.
+    ///
+    /// Invalid escape is kept as is, and invalid UTF-8 is replaced with
+    /// [`U+FFFD`][std::char::REPLACEMENT_CHARACTER].
+    pub fn decoded_path(&self) -> Cow<'_, str> {
+        percent_decode(&self.path)
+    }
+
+    /// Returns the percent decoded query string, `+` is not decoded as space.
+    ///
+    /// see [`decoded_path`][Self::decoded_path] for decoding behavior
+    pub fn decoded_query(&self) -> Option<Cow<'_, str>> {
+        self.query.as_deref().map(percent_decode)
+    }
+
+    /// Extracts a string slice containing the entire [`Uri`].
+    pub fn as_str(&self) -> &str {
+        self.value.as_str()
+    }
+
+    /// Returns [`Uri`] with path prefix removed, or [`None`] if path is not under `prefix`.
+    ///
+    /// `prefix` match whole segments, `/api` match `/api` and `/api/users` but not `/apis`.
+    pub(crate) fn strip_prefix(&self, prefix: &str) -> Option<Uri> {
+        let rest = self.path.strip_prefix(prefix)?;
+        let path = match rest {
+            "" => ByteStr::from_static("/"),
+            rest if rest.starts_with('/') => self.path.slice_ref(rest),
+            _ => return None,
+        };
+
+        let mut value = String::with_capacity(self.value.len() - prefix.len() + 1);
+        if let (Some(scheme), Some(authority)) = (&self.scheme, &self.authority) {
+            value.push_str(scheme);
+            value.push_str("://");
+            value.push_str(authority);
+        }
+        value.push_str(&path);
+        if let Some(query) = &self.query {
+            value.push('?');
+            value.push_str(query);
+        }
+
+        Some(Self {
+            value: value.into(),
+            scheme: self.scheme.clone(),
+            authority: self.authority.clone(),
+            path,
+            query: self.query.clone(),
+        })
+    }
+}
+
+fn split_query(value: &ByteStr) -> Result<(ByteStr, Option<ByteStr>), InvalidUri> {
+    if value.contains('#') {
+        return Err(InvalidUri::InvalidChar);
+    }
+    Ok(match value.split_once('?') {
+        Some((path, query)) => (value.slice_ref(path), Some(value.slice_ref(query))),
+        None => (value.clone(), None),
+    })
+}
+
+fn is_scheme(scheme: &str) -> bool {
+    let mut bytes = scheme.bytes();
+    matches!(bytes.next(), Some(b) if b.is_ascii_alphabetic())
+        && bytes.all(|b| b.is_ascii_alphanumeric() || matches!(b, b'+' | b'-' | b'.'))
+}
+
+/// Percent decode a str.
+///
+/// Invalid escape is kept as is, and invalid UTF-8 is replaced with
+/// [`U+FFFD`][std::char::REPLACEMENT_CHARACTER].
+pub(crate) fn percent_decode(value: &str) -> Cow<'_, str> {
+    if !value.contains('%') {
+        return Cow::Borrowed(value);
+    }
+
+    fn hex(b: u8) -> Option<u8> {
+        (b as char).to_digit(16).map(|d| d as u8)
+    }
+
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && let Some(&[hi, lo]) = bytes.get(i + 1..i + 3)
+            && let (Some(hi), Some(lo)) = (hex(hi), hex(lo))
+        {
+            decoded.push(hi << 4 | lo);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+
+    match String::from_utf8(decoded) {
+        Ok(ok) => Cow::Owned(ok),
+        Err(err) => Cow::Owned(String::from_utf8_lossy(err.as_bytes()).into_owned()),
+    }
+}
+
+impl Default for Uri {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::str::FromStr for Uri {
+    type Err = InvalidUri;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::try_copy_from_str(s)
+    }
+}
+
+impl std::fmt::Display for Uri {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::fmt::Debug for Uri {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+// ===== Error =====
+
+/// Error when parsing [`Uri`].
+#[derive(Debug)]
+pub enum InvalidUri {
+    /// Uri is empty.
+    Empty,
+    /// Uri contains non visible ASCII or fragment.
+    InvalidChar,
+    /// Scheme in absolute-form is invalid.
+    InvalidScheme,
+    /// Authority is empty or contains invalid character.
+    InvalidAuthority,
+}
+
+impl std::error::Error for InvalidUri { }
+
+impl std::fmt::Display for InvalidUri {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            InvalidUri::Empty => "uri is empty",
+            InvalidUri::InvalidChar => "uri contains invalid character",
+            InvalidUri::InvalidScheme => "uri contains invalid scheme",
+            InvalidUri::InvalidAuthority => "uri contains invalid authority",
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn request_target_forms() {
+        let uri = Uri::from_static("/users?id=1");
+        assert_eq!(uri.scheme(), None);
+        assert_eq!(uri.authority(), None);
+        assert_eq!(uri.path(), "/users");
+        assert_eq!(uri.query(), Some("id=1"));
+
+        let uri = Uri::from_static("http://example.com:8080/users?id=1");
+        assert_eq!(uri.scheme(), Some("http"));
+        assert_eq!(uri.authority(), Some("example.com:8080"));
+        assert_eq!(uri.path(), "/users");
+        assert_eq!(uri.query(), Some("id=1"));
+
+        let uri = Uri::from_static("https://example.com?id=1");
+        assert_eq!(uri.authority(), Some("example.com"));
+        assert_eq!(uri.path(), "/");
+        assert_eq!(uri.query(), Some("id=1"));
+
+        let uri = Uri::from_static("example.com:443");
+        assert!(uri.is_authority_form());
+        assert_eq!(uri.authority(), Some("example.com:443"));
+        assert_eq!(uri.path(), "");
+
+        let uri = Uri::from_static("*");
+        assert!(uri.is_asterisk());
+        assert_eq!(uri.path(), "*");
+    }
+
+    #[test]
+    fn invalid_uri() {
+        assert!(matches!("".parse::<Uri>(), Err(InvalidUri::Empty)));
+        assert!(matches!("/a b".parse::<Uri>(), Err(InvalidUri::InvalidChar)));
+        assert!(matches!("/a#b".parse::<Uri>(), Err(InvalidUri::InvalidChar)));
+        assert!(matches!("1http://a/".parse::<Uri>(), Err(InvalidUri::InvalidScheme)));
+        assert!(matches!("http:///a".parse::<Uri>(), Err(InvalidUri::InvalidAuthority)));
+        assert!(matches!("a/b".parse::<Uri>(), Err(InvalidUri::InvalidAuthority)));
+    }
+
+    #[test]
+    fn decode() {
+        assert_eq!(percent_decode("/a%20b"), "/a b");
+        assert_eq!(percent_decode("/caf%C3%A9"), "/café");
+        assert_eq!(percent_decode("/100%"), "/100%");
+        assert_eq!(percent_decode("/%zz"), "/%zz");
+        assert!(matches!(percent_decode("/plain"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn strip_prefix() {
+        let uri = Uri::from_static("/api/users?id=1");
+        let stripped = uri.strip_prefix("/api").unwrap();
+        assert_eq!(stripped.path(), "/users");
+        assert_eq!(stripped.query(), Some("id=1"));
+        assert_eq!(stripped.as_str(), "/users?id=1");
+
+        let stripped = Uri::from_static("/api").strip_prefix("/api").unwrap();
+        assert_eq!(stripped.as_str(), "/");
+
+        let stripped = Uri::from_static("http://example.com/api/a").strip_prefix("/api").unwrap();
+        assert_eq!(stripped.as_str(), "http://example.com/a");
+
+        assert!(Uri::from_static("/apis").strip_prefix("/api").is_none());
+        assert!(Uri::from_static("/a").strip_prefix("/api").is_none());
+    }
+}
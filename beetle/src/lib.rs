@@ -6,6 +6,7 @@ mod docs;
 pub mod common;
 pub mod io;
 pub mod net;
+#[cfg_attr(not(feature = "log"), allow(unused))]
 mod ext;
 
 pub mod http;
@@ -18,12 +19,16 @@ mod futures;
 
 pub mod service;
 pub mod routing;
+pub mod middleware;
 
 pub mod runtime;
 
+#[cfg(test)]
+mod testing;
+
 pub use request::{Request, FromRequest, FromRequestParts};
 pub use response::{Response, IntoResponse, IntoResponseParts};
-pub use routing::{Router, get, post, put, patch, delete};
+pub use routing::{Router, get, post, put, patch, delete, head, options, any};
 pub use service::{Service, HttpService};
 
 #[cfg(feature = "tokio")]
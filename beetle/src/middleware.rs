@@ -0,0 +1,26 @@
+//! middleware layers
+//!
+//! middleware is applied to [`Router`][crate::Router] using [`Router::layer`]
+//!
+//! [`Router::layer`]: crate::Router::layer
+mod from_fn;
+mod cors;
+
+#[cfg(any(feature = "compression", feature = "tokio"))]
+pub(crate) mod encoding;
+#[cfg(feature = "compression")]
+mod codec;
+#[cfg(feature = "compression")]
+mod compression;
+#[cfg(feature = "compression")]
+mod decompression;
+
+pub use cors::{AllowOrigin, Cors, CorsFuture, CorsService};
+pub use from_fn::{from_fn, FromFn, FromFnLayer, Next, NextFuture};
+
+#[cfg(feature = "compression")]
+pub use codec::Level;
+#[cfg(feature = "compression")]
+pub use compression::{Compression, CompressionFuture, CompressionService};
+#[cfg(feature = "compression")]
+pub use decompression::{RequestDecompression, RequestDecompressionService};
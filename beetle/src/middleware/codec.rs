@@ -0,0 +1,169 @@
+//! content coding encoder and decoder
+use bytes::Bytes;
+use std::io::{self, Read, Write};
+
+use super::encoding::Encoding;
+
+impl Encoding {
+    pub(crate) fn encoder(self, level: Level) -> Encoder {
+        let buf = Vec::new();
+        let inner = match self {
+            Encoding::Gzip => {
+                let level = level.flate2();
+                EncoderKind::Gzip(flate2::write::GzEncoder::new(buf, level))
+            }
+            Encoding::Deflate => {
+                let level = level.flate2();
+                EncoderKind::Deflate(flate2::write::ZlibEncoder::new(buf, level))
+            }
+            Encoding::Br => {
+                let quality = match level {
+                    Level::Fastest => 1,
+                    Level::Default => 4,
+                    Level::Best => 11,
+                };
+                EncoderKind::Br(Box::new(brotli::CompressorWriter::new(buf, 4096, quality, 22)))
+            }
+            Encoding::Zstd => {
+                let level = match level {
+                    Level::Fastest => 1,
+                    Level::Default => zstd::DEFAULT_COMPRESSION_LEVEL,
+                    Level::Best => 19,
+                };
+                let encoder = zstd::stream::write::Encoder::new(buf, level)
+                    .expect("failed to create zstd encoder");
+                EncoderKind::Zstd(encoder)
+            }
+        };
+        Encoder { inner }
+    }
+
+    /// decode `data`, returns [`None`] if decoded data exceeds `limit`
+    ///
+    /// decoding stops once the limit is exceeded, so decompression bomb does not exhaust
+    /// memory
+    pub(crate) fn decode(self, data: &[u8], limit: usize) -> io::Result<Option<Bytes>> {
+        let reader: Box<dyn Read + '_> = match self {
+            Encoding::Gzip => Box::new(flate2::read::MultiGzDecoder::new(data)),
+            Encoding::Deflate => Box::new(flate2::read::ZlibDecoder::new(data)),
+            Encoding::Br => Box::new(brotli::Decompressor::new(data, 4096)),
+            Encoding::Zstd => Box::new(zstd::stream::read::Decoder::with_buffer(data)?),
+        };
+        let mut decoded = Vec::new();
+        let take = (limit as u64).saturating_add(1);
+        reader.take(take).read_to_end(&mut decoded)?;
+        match decoded.len() > limit {
+            true => Ok(None),
+            false => Ok(Some(decoded.into())),
+        }
+    }
+}
+
+/// compression level
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Level {
+    /// fastest compression
+    Fastest,
+    /// balance between speed and size
+    #[default]
+    Default,
+    /// smallest size
+    Best,
+}
+
+impl Level {
+    fn flate2(self) -> flate2::Compression {
+        match self {
+            Level::Fastest => flate2::Compression::fast(),
+            Level::Default => flate2::Compression::default(),
+            Level::Best => flate2::Compression::best(),
+        }
+    }
+}
+
+/// incremental encoder, encoded data is taken after each write
+pub(crate) struct Encoder {
+    inner: EncoderKind,
+}
+
+enum EncoderKind {
+    Gzip(flate2::write::GzEncoder<Vec<u8>>),
+    Deflate(flate2::write::ZlibEncoder<Vec<u8>>),
+    Br(Box<brotli::CompressorWriter<Vec<u8>>>),
+    Zstd(zstd::stream::write::Encoder<'static, Vec<u8>>),
+}
+
+impl Encoder {
+    /// encode data, returns the available encoded data which may be empty
+    pub(crate) fn encode(&mut self, data: &[u8]) -> io::Result<Bytes> {
+        self.write(data, false)
+    }
+
+    /// encode data and flush the encoder, so the returned data can be decoded without
+    /// waiting for the rest of the stream, e.g: server-sent events
+    pub(crate) fn encode_flush(&mut self, data: &[u8]) -> io::Result<Bytes> {
+        self.write(data, true)
+    }
+
+    fn write(&mut self, data: &[u8], flush: bool) -> io::Result<Bytes> {
+        fn write<W: Write>(writer: &mut W, data: &[u8], flush: bool) -> io::Result<()> {
+            writer.write_all(data)?;
+            if flush {
+                writer.flush()?;
+            }
+            Ok(())
+        }
+
+        let buf = match &mut self.inner {
+            EncoderKind::Gzip(e) => {
+                write(e, data, flush)?;
+                e.get_mut()
+            }
+            EncoderKind::Deflate(e) => {
+                write(e, data, flush)?;
+                e.get_mut()
+            }
+            EncoderKind::Br(e) => {
+                write(e.as_mut(), data, flush)?;
+                e.get_mut()
+            }
+            EncoderKind::Zstd(e) => {
+                write(e, data, flush)?;
+                e.get_mut()
+            }
+        };
+        Ok(std::mem::take(buf).into())
+    }
+
+    /// finish encoding, returns the remaining encoded data
+    pub(crate) fn finish(self) -> io::Result<Bytes> {
+        let buf = match self.inner {
+            EncoderKind::Gzip(e) => e.finish()?,
+            EncoderKind::Deflate(e) => e.finish()?,
+            EncoderKind::Br(e) => e.into_inner(),
+            EncoderKind::Zstd(e) => e.finish()?,
+        };
+        Ok(buf.into())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        let data = "hello world ".repeat(1000);
+        for encoding in Encoding::PREFERENCE {
+            let mut encoder = encoding.encoder(Level::Default);
+            let mut encoded = encoder.encode(data.as_bytes()).unwrap().to_vec();
+            encoded.extend_from_slice(&encoder.finish().unwrap());
+            assert!(encoded.len() < data.len());
+
+            let decoded = encoding.decode(&encoded, data.len()).unwrap().unwrap();
+            assert_eq!(decoded, data.as_bytes());
+            assert!(encoding.decode(&encoded, data.len() - 1).unwrap().is_none());
+            assert!(encoding.decode(b"not encoded", data.len()).is_err());
+        }
+    }
+}
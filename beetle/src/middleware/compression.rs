@@ -0,0 +1,484 @@
+use bytes::Bytes;
+use futures_core::Stream;
+use std::{
+    convert::Infallible,
+    io,
+    pin::Pin,
+    task::{Context, Poll, ready},
+};
+
+use super::{
+    codec::{Encoder, Level},
+    encoding::Encoding,
+};
+use crate::{
+    headers::{HeaderMap, HeaderValue},
+    helpers::Layer,
+    http::{Method, StatusCode},
+    request::Request,
+    response::{Body, Response},
+    service::{HttpService, Service},
+};
+
+/// response compression [`Layer`]
+///
+/// the encoding is negotiated from request `Accept-Encoding`, response is not compressed if:
+///
+/// - body is smaller than [`min_size`][Compression::min_size]
+/// - content type is already compressed, e.g: images, videos, or archives
+/// - response already has `Content-Encoding`, `Content-Range`, or `Cache-Control: no-transform`
+/// - response is `206 Partial Content`, the ranges is of the uncompressed representation
+///
+/// both buffered and streaming body is supported, compressed streaming body is sent with
+/// chunked transfer encoding
+///
+/// ```
+/// use beetle::{Router, middleware::Compression};
+///
+/// let router = Router::new().layer(Compression::new().min_size(512));
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Compression {
+    gzip: bool,
+    deflate: bool,
+    br: bool,
+    zstd: bool,
+    min_size: usize,
+    level: Level,
+}
+
+impl Compression {
+    /// create new [`Compression`] with all encoding enabled
+    pub fn new() -> Self {
+        Self { gzip: true, deflate: true, br: true, zstd: true, min_size: 256, level: Level::Default }
+    }
+
+    /// enable or disable gzip encoding
+    pub fn gzip(mut self, enable: bool) -> Self {
+        self.gzip = enable;
+        self
+    }
+
+    /// enable or disable deflate encoding
+    pub fn deflate(mut self, enable: bool) -> Self {
+        self.deflate = enable;
+        self
+    }
+
+    /// enable or disable brotli encoding
+    pub fn br(mut self, enable: bool) -> Self {
+        self.br = enable;
+        self
+    }
+
+    /// enable or disable zstd encoding
+    pub fn zstd(mut self, enable: bool) -> Self {
+        self.zstd = enable;
+        self
+    }
+
+    /// minimum body length to be compressed, default to 256 bytes
+    ///
+    /// streaming body with unknown length is always compressed
+    pub fn min_size(mut self, min_size: usize) -> Self {
+        self.min_size = min_size;
+        self
+    }
+
+    /// set compression level
+    pub fn level(mut self, level: Level) -> Self {
+        self.level = level;
+        self
+    }
+
+    fn is_enabled(&self, encoding: Encoding) -> bool {
+        match encoding {
+            Encoding::Gzip => self.gzip,
+            Encoding::Deflate => self.deflate,
+            Encoding::Br => self.br,
+            Encoding::Zstd => self.zstd,
+        }
+    }
+
+    fn negotiate(&self, headers: &HeaderMap) -> Option<Encoding> {
+        let accept = headers.get_all("accept-encoding").filter_map(|e| e.as_str().ok());
+        Encoding::negotiate(accept, |e| self.is_enabled(e))
+    }
+
+    /// returns `true` if response can be compressed regardless of client encoding
+    fn is_compressible(&self, res: &Response) -> bool {
+        if matches!(
+            res.status(),
+            StatusCode::NO_CONTENT | StatusCode::NOT_MODIFIED | StatusCode::PARTIAL_CONTENT
+        ) {
+            return false;
+        }
+        let headers = res.headers();
+        if headers.get("content-encoding").is_some() || headers.get("content-range").is_some() {
+            return false;
+        }
+        if let Some(cache) = headers.get("cache-control")
+            && cache.as_str().is_ok_and(|e| e.to_ascii_lowercase().contains("no-transform"))
+        {
+            return false;
+        }
+        if let Some(ty) = headers.get("content-type")
+            && !is_compressible_type(ty.as_str().unwrap_or_default())
+        {
+            return false;
+        }
+        res.body().content_len().is_none_or(|len| len >= self.min_size)
+    }
+
+    fn compress(&self, res: Response, encoding: Option<Encoding>) -> Response {
+        if !self.is_compressible(&res) {
+            return res;
+        }
+        let (mut parts, body) = res.into_parts();
+        parts.headers_mut().append("vary", HeaderValue::from_static("accept-encoding"));
+
+        let Some(encoding) = encoding else {
+            return Response::from_parts(parts, body);
+        };
+        let mut encoder = encoding.encoder(self.level);
+
+        let body = match body.try_into_bytes() {
+            Ok(bytes) => {
+                let encoded = encoder.encode(&bytes).and_then(|head| {
+                    let tail = encoder.finish()?;
+                    Ok([head, tail].concat())
+                });
+                match encoded {
+                    Ok(encoded) => Body::bytes(encoded),
+                    // writing into vec never fails
+                    Err(_) => return Response::from_parts(parts, Body::bytes(bytes)),
+                }
+            }
+            Err(body) => Body::stream(CompressStream { body, encoder: Some(encoder) }),
+        };
+
+        let headers = parts.headers_mut();
+        headers.remove("content-length");
+        headers.insert("content-encoding", HeaderValue::from_static(encoding.as_str()));
+        // compressed representation is no longer byte-for-byte identical
+        if let Some(etag) = headers.get("etag")
+            && !etag.as_bytes().starts_with(b"W/")
+        {
+            let mut weak = b"W/".to_vec();
+            weak.extend_from_slice(etag.as_bytes());
+            if let Ok(weak) = HeaderValue::try_from_slice(weak) {
+                headers.insert("etag", weak);
+            }
+        }
+        Response::from_parts(parts, body)
+    }
+}
+
+impl Default for Compression {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> Layer<S> for Compression {
+    type Service = CompressionService<S>;
+
+    fn layer(self, inner: S) -> Self::Service {
+        CompressionService { compression: self, inner }
+    }
+}
+
+/// returns `false` for already compressed content type
+fn is_compressible_type(ty: &str) -> bool {
+    let ty = ty.split(';').next().unwrap_or_default().trim().to_ascii_lowercase();
+    if ty == "image/svg+xml" {
+        return true;
+    }
+    if ty.starts_with("image/") || ty.starts_with("audio/") || ty.starts_with("video/") {
+        return false;
+    }
+    !matches!(
+        ty.as_str(),
+        "application/gzip"
+            | "application/x-gzip"
+            | "application/zip"
+            | "application/zstd"
+            | "application/x-bzip2"
+            | "application/x-xz"
+            | "application/x-7z-compressed"
+            | "application/x-rar-compressed"
+            | "application/pdf"
+            | "application/octet-stream"
+            | "application/wasm"
+            | "font/woff"
+            | "font/woff2"
+            | "text/event-stream"
+    )
+}
+
+// ===== Service =====
+
+/// service returned by [`Compression`] layer
+#[derive(Debug, Clone)]
+pub struct CompressionService<S> {
+    compression: Compression,
+    inner: S,
+}
+
+impl<S> Service<Request> for CompressionService<S>
+where
+    S: HttpService,
+{
+    type Response = Response;
+    type Error = Infallible;
+    type Future = CompressionFuture<S::Future>;
+
+    fn call(&self, req: Request) -> Self::Future {
+        let encoding = self.compression.negotiate(req.headers());
+        CompressionFuture {
+            inner: self.inner.call(req),
+            compression: self.compression,
+            encoding,
+        }
+    }
+
+    fn methods(&self) -> Option<&[Method]> {
+        self.inner.methods()
+    }
+}
+
+pin_project_lite::pin_project! {
+    /// future returned by [`CompressionService`]
+    pub struct CompressionFuture<F> {
+        #[pin]
+        inner: F,
+        compression: Compression,
+        encoding: Option<Encoding>,
+    }
+}
+
+impl<F> Future for CompressionFuture<F>
+where
+    F: Future<Output = Result<Response, Infallible>>,
+{
+    type Output = Result<Response, Infallible>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let me = self.project();
+        let Ok(res) = ready!(me.inner.poll(cx));
+        Poll::Ready(Ok(me.compression.compress(res, *me.encoding)))
+    }
+}
+
+// ===== Stream =====
+
+/// compress streaming body
+struct CompressStream {
+    body: Body,
+    encoder: Option<Encoder>,
+}
+
+impl Stream for CompressStream {
+    type Item = io::Result<Bytes>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let me = self.get_mut();
+        loop {
+            let Some(encoder) = me.encoder.as_mut() else {
+                return Poll::Ready(None);
+            };
+            let encoded = match ready!(me.body.poll_data(cx)) {
+                // each chunk is flushed, so the client does not wait for the next chunk
+                Some(Ok(data)) => encoder.encode_flush(&data),
+                Some(Err(err)) => {
+                    me.encoder = None;
+                    return Poll::Ready(Some(Err(err)));
+                }
+                None => me.encoder.take().unwrap().finish(),
+            };
+            match encoded {
+                // encoder may buffer the data
+                Ok(encoded) if encoded.is_empty() => continue,
+                Ok(encoded) => return Poll::Ready(Some(Ok(encoded))),
+                Err(err) => {
+                    me.encoder = None;
+                    return Poll::Ready(Some(Err(err)));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        service::servicefn::service_fn,
+        testing::{body, call, request, stream},
+    };
+
+    fn respond(f: fn() -> Response) -> impl HttpService {
+        service_fn(move |_| std::future::ready(Ok::<_, Infallible>(f())))
+    }
+
+    fn text() -> Response {
+        Response::new(Body::bytes("hello world ".repeat(100)))
+    }
+
+    fn header<'a>(res: &'a Response, name: &str) -> Option<&'a str> {
+        res.headers().get(name).and_then(|value| value.as_str().ok())
+    }
+
+    fn gzip(service: &impl HttpService) -> Response {
+        call(service, request(Method::GET, "/", &[("accept-encoding", "gzip")]))
+    }
+
+    #[test]
+    fn compress() {
+        let service = Compression::new().layer(respond(text));
+        let res = gzip(&service);
+        assert_eq!(header(&res, "content-encoding"), Some("gzip"));
+        assert_eq!(header(&res, "vary"), Some("accept-encoding"));
+
+        let decoded = Encoding::Gzip.decode(&body(res), usize::MAX).unwrap().unwrap();
+        assert_eq!(decoded, "hello world ".repeat(100).as_bytes());
+
+        // not accepted by client, but still varies
+        let res = call(&service, request(Method::GET, "/", &[]));
+        assert_eq!(header(&res, "content-encoding"), None);
+        assert_eq!(header(&res, "vary"), Some("accept-encoding"));
+        assert_eq!(res.body().content_len(), Some(1200));
+    }
+
+    #[test]
+    fn min_size() {
+        let service = Compression::new().min_size(1201).layer(respond(text));
+        let res = gzip(&service);
+        assert_eq!(header(&res, "content-encoding"), None);
+        assert_eq!(header(&res, "vary"), None);
+
+        let service = Compression::new().min_size(1200).layer(respond(text));
+        assert_eq!(header(&gzip(&service), "content-encoding"), Some("gzip"));
+    }
+
+    #[test]
+    fn skip() {
+        fn png() -> Response {
+            let mut res = text();
+            res.headers_mut().insert("content-type", HeaderValue::from_static("image/png"));
+            res
+        }
+        fn no_transform() -> Response {
+            let mut res = text();
+            res.headers_mut().insert("cache-control", HeaderValue::from_static("public, No-Transform"));
+            res
+        }
+        fn partial() -> Response {
+            let mut res = text();
+            *res.status_mut() = StatusCode::PARTIAL_CONTENT;
+            res
+        }
+        fn svg() -> Response {
+            let mut res = text();
+            res.headers_mut().insert("content-type", HeaderValue::from_static("image/svg+xml"));
+            res
+        }
+
+        for f in [png, no_transform, partial] {
+            let res = gzip(&Compression::new().layer(respond(f)));
+            assert_eq!(header(&res, "content-encoding"), None);
+            assert_eq!(res.body().content_len(), Some(1200));
+        }
+        let res = gzip(&Compression::new().layer(respond(svg)));
+        assert_eq!(header(&res, "content-encoding"), Some("gzip"));
+    }
+
+    #[test]
+    fn weak_etag() {
+        fn etag() -> Response {
+            let mut res = text();
+            res.headers_mut().insert("etag", HeaderValue::from_static("\"abc\""));
+            res
+        }
+        fn weak() -> Response {
+            let mut res = text();
+            res.headers_mut().insert("etag", HeaderValue::from_static("W/\"abc\""));
+            res
+        }
+
+        let res = gzip(&Compression::new().layer(respond(etag)));
+        assert_eq!(header(&res, "etag"), Some("W/\"abc\""));
+        let res = gzip(&Compression::new().layer(respond(weak)));
+        assert_eq!(header(&res, "etag"), Some("W/\"abc\""));
+
+        // uncompressed response keep its strong etag
+        let res = call(&Compression::new().layer(respond(etag)), request(Method::GET, "/", &[]));
+        assert_eq!(header(&res, "etag"), Some("\"abc\""));
+    }
+
+    #[test]
+    fn streaming() {
+        fn chunks() -> Response {
+            Response::new(Body::stream(stream(&["hello ", "streaming ", "world"])))
+        }
+
+        // streaming body is compressed regardless of min size
+        let service = Compression::new().min_size(usize::MAX).layer(respond(chunks));
+        let res = gzip(&service);
+        assert_eq!(header(&res, "content-encoding"), Some("gzip"));
+        assert_eq!(res.body().content_len(), None);
+
+        let decoded = Encoding::Gzip.decode(&body(res), usize::MAX).unwrap().unwrap();
+        assert_eq!(decoded, "hello streaming world");
+    }
+
+    #[test]
+    fn streaming_flush() {
+        use std::io::Read;
+
+        /// yield a single event, then never ends
+        struct Event(Option<Bytes>);
+
+        impl Stream for Event {
+            type Item = io::Result<Bytes>;
+
+            fn poll_next(mut self: Pin<&mut Self>, _: &mut Context) -> Poll<Option<Self::Item>> {
+                match self.0.take() {
+                    Some(event) => Poll::Ready(Some(Ok(event))),
+                    None => Poll::Pending,
+                }
+            }
+        }
+
+        fn event() -> Response {
+            Response::new(Body::stream(Event(Some(Bytes::from_static(b"data: hello\n\n")))))
+        }
+
+        let service = Compression::new().layer(respond(event));
+        for encoding in Encoding::PREFERENCE {
+            let res = call(&service, request(Method::GET, "/", &[("accept-encoding", encoding.as_str())]));
+            assert_eq!(header(&res, "content-encoding"), Some(encoding.as_str()));
+
+            let mut body = res.into_body();
+            let mut cx = Context::from_waker(std::task::Waker::noop());
+            let Poll::Ready(Some(Ok(data))) = body.poll_data(&mut cx) else {
+                panic!("{} chunk is not flushed", encoding.as_str());
+            };
+            assert!(body.poll_data(&mut cx).is_pending());
+
+            // the stream is not finished, decode what is available
+            let mut reader: Box<dyn Read> = match encoding {
+                Encoding::Gzip => Box::new(flate2::read::GzDecoder::new(&data[..])),
+                Encoding::Deflate => Box::new(flate2::read::ZlibDecoder::new(&data[..])),
+                Encoding::Br => Box::new(brotli::Decompressor::new(&data[..], 4096)),
+                Encoding::Zstd => Box::new(zstd::stream::read::Decoder::new(&data[..]).unwrap()),
+            };
+            let mut decoded = Vec::new();
+            let mut buf = [0; 64];
+            while let Ok(n @ 1..) = reader.read(&mut buf) {
+                decoded.extend_from_slice(&buf[..n]);
+            }
+            assert_eq!(decoded, b"data: hello\n\n", "{}", encoding.as_str());
+        }
+    }
+}
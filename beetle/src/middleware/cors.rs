@@ -0,0 +1,510 @@
+use std::{
+    convert::Infallible,
+    future::Ready,
+    sync::Arc,
+    task::{Poll, ready},
+    time::Duration,
+};
+
+use crate::{
+    common::ByteStr,
+    futures::{EitherInto, FutureExt},
+    headers::{HeaderMap, HeaderValue},
+    helpers::Layer,
+    http::{Method, StatusCode},
+    request::Request,
+    response::{IntoResponse, Response},
+    service::{HttpService, Service},
+};
+
+const ALLOW_ORIGIN: &str = "access-control-allow-origin";
+const ALLOW_CREDENTIALS: &str = "access-control-allow-credentials";
+const ALLOW_METHODS: &str = "access-control-allow-methods";
+const ALLOW_HEADERS: &str = "access-control-allow-headers";
+const EXPOSE_HEADERS: &str = "access-control-expose-headers";
+const MAX_AGE: &str = "access-control-max-age";
+const REQUEST_METHOD: &str = "access-control-request-method";
+const REQUEST_HEADERS: &str = "access-control-request-headers";
+
+/// cross-origin resource sharing [`Layer`]
+///
+/// preflight request is answered by the layer itself without reaching the inner service,
+/// other request with allowed `Origin` get the `Access-Control-*` headers added to its
+/// response
+///
+/// by default, no origin is allowed
+///
+/// ```
+/// use beetle::{Router, http::Method, middleware::{AllowOrigin, Cors}};
+///
+/// let cors = Cors::new()
+///     .allow_origin(AllowOrigin::list(["https://example.com", "https://app.example.com"]))
+///     .allow_methods([Method::GET, Method::POST])
+///     .allow_headers(["content-type"])
+///     .allow_credentials(true);
+///
+/// let router = Router::new().layer(cors);
+/// ```
+#[derive(Clone)]
+pub struct Cors {
+    origin: AllowOrigin,
+    /// [`None`] mirror the requested method
+    methods: Option<ByteStr>,
+    /// [`None`] mirror the requested headers
+    headers: Option<ByteStr>,
+    expose: Option<ByteStr>,
+    credentials: bool,
+    max_age: Option<Duration>,
+}
+
+impl Cors {
+    /// create new [`Cors`] which does not allow any origin
+    pub fn new() -> Self {
+        Self {
+            origin: AllowOrigin::default(),
+            methods: Some(ByteStr::new()),
+            headers: Some(ByteStr::new()),
+            expose: None,
+            credentials: false,
+            max_age: None,
+        }
+    }
+
+    /// create new [`Cors`] which allow any origin, method and header
+    ///
+    /// credentials is not allowed
+    pub fn permissive() -> Self {
+        Self::new()
+            .allow_origin(AllowOrigin::any())
+            .allow_any_method()
+            .allow_any_header()
+    }
+
+    /// set allowed origins
+    pub fn allow_origin(mut self, origin: AllowOrigin) -> Self {
+        self.origin = origin;
+        self
+    }
+
+    /// set allowed methods
+    pub fn allow_methods(mut self, methods: impl IntoIterator<Item = Method>) -> Self {
+        self.methods = Some(join(methods.into_iter().map(|m| m.as_str().to_owned())));
+        self
+    }
+
+    /// allow any method by mirroring the requested method
+    pub fn allow_any_method(mut self) -> Self {
+        self.methods = None;
+        self
+    }
+
+    /// set allowed request headers
+    ///
+    /// # Panics
+    ///
+    /// panics if header name contains invalid character
+    pub fn allow_headers<I>(mut self, headers: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        self.headers = Some(join(headers.into_iter().map(Into::into)));
+        self
+    }
+
+    /// allow any request header by mirroring the requested headers
+    pub fn allow_any_header(mut self) -> Self {
+        self.headers = None;
+        self
+    }
+
+    /// set response headers exposed to the client
+    ///
+    /// # Panics
+    ///
+    /// panics if header name contains invalid character
+    pub fn expose_headers<I>(mut self, headers: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        self.expose = Some(join(headers.into_iter().map(Into::into)));
+        self
+    }
+
+    /// allow request with credentials
+    ///
+    /// credentials cannot be allowed with [`AllowOrigin::any`], list the allowed origins
+    /// instead, the layer panics otherwise
+    pub fn allow_credentials(mut self, credentials: bool) -> Self {
+        self.credentials = credentials;
+        self
+    }
+
+    /// set how long preflight response can be cached
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// returns the `Access-Control-Allow-Origin` value if the origin is allowed
+    fn allowed_origin(&self, origin: &HeaderValue) -> Option<HeaderValue> {
+        let value = origin.as_str().ok()?;
+        let allowed = match &self.origin.0 {
+            Origin::None => false,
+            Origin::Any => return Some(HeaderValue::from_static("*")),
+            Origin::List(list) => list.iter().any(|e| e == value),
+            Origin::Predicate(f) => f(value),
+        };
+        allowed.then(|| HeaderValue::from_string(ByteStr::copy_from_str(value)))
+    }
+
+    /// headers added to response of non preflight request
+    fn headers(&self, origin: Option<&HeaderValue>) -> Vec<(&'static str, HeaderValue)> {
+        let mut headers = Vec::with_capacity(4);
+        if !matches!(self.origin.0, Origin::Any) {
+            headers.push(("vary", HeaderValue::from_static("origin")));
+        }
+        let Some(origin) = origin.and_then(|origin| self.allowed_origin(origin)) else {
+            return headers;
+        };
+        headers.push((ALLOW_ORIGIN, origin));
+        if self.credentials {
+            headers.push((ALLOW_CREDENTIALS, HeaderValue::from_static("true")));
+        }
+        if let Some(expose) = &self.expose {
+            headers.push((EXPOSE_HEADERS, HeaderValue::from_string(expose.clone())));
+        }
+        headers
+    }
+
+    /// response of preflight request
+    fn preflight(&self, req: &Request) -> Response {
+        let mut res = StatusCode::NO_CONTENT.into_response();
+        let headers = res.headers_mut();
+        headers.insert(
+            "vary",
+            HeaderValue::from_static("origin, access-control-request-method, access-control-request-headers"),
+        );
+
+        let Some(origin) = req.headers().get("origin").and_then(|e| self.allowed_origin(e)) else {
+            return res;
+        };
+        headers.insert(ALLOW_ORIGIN, origin);
+        if self.credentials {
+            headers.insert(ALLOW_CREDENTIALS, HeaderValue::from_static("true"));
+        }
+
+        let methods = match &self.methods {
+            Some(methods) if methods.is_empty() => None,
+            Some(methods) => Some(HeaderValue::from_string(methods.clone())),
+            None => mirror(req.headers(), REQUEST_METHOD),
+        };
+        if let Some(methods) = methods {
+            headers.insert(ALLOW_METHODS, methods);
+        }
+
+        let allow_headers = match &self.headers {
+            Some(allow) if allow.is_empty() => None,
+            Some(allow) => Some(HeaderValue::from_string(allow.clone())),
+            None => mirror(req.headers(), REQUEST_HEADERS),
+        };
+        if let Some(allow_headers) = allow_headers {
+            headers.insert(ALLOW_HEADERS, allow_headers);
+        }
+
+        if let Some(max_age) = self.max_age {
+            headers.insert(MAX_AGE, HeaderValue::from_string(max_age.as_secs().to_string()));
+        }
+        res
+    }
+}
+
+impl Default for Cors {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> Layer<S> for Cors {
+    type Service = CorsService<S>;
+
+    /// # Panics
+    ///
+    /// panics if credentials is allowed with [`AllowOrigin::any`]
+    fn layer(self, inner: S) -> Self::Service {
+        assert!(
+            !(self.credentials && matches!(self.origin.0, Origin::Any)),
+            "cors credentials cannot be allowed with any origin, use `AllowOrigin::list` instead",
+        );
+        CorsService { cors: self, inner }
+    }
+}
+
+/// join header values, and validate it
+fn join(values: impl Iterator<Item = String>) -> ByteStr {
+    let value = values.collect::<Vec<_>>().join(", ");
+    if HeaderValue::try_copy_from_string(&value).is_err() {
+        panic!("cors header contains invalid character: {value:?}");
+    }
+    value.into()
+}
+
+/// copy request header value
+fn mirror(headers: &HeaderMap, name: &str) -> Option<HeaderValue> {
+    let value = headers.get(name)?;
+    HeaderValue::try_copy_from_slice(value.as_bytes()).ok()
+}
+
+// ===== AllowOrigin =====
+
+/// allowed origins of [`Cors`]
+#[derive(Clone, Default)]
+pub struct AllowOrigin(Origin);
+
+#[derive(Clone, Default)]
+enum Origin {
+    #[default]
+    None,
+    Any,
+    List(Vec<ByteStr>),
+    Predicate(Arc<dyn Fn(&str) -> bool + Send + Sync>),
+}
+
+impl AllowOrigin {
+    /// allow any origin
+    pub fn any() -> Self {
+        Self(Origin::Any)
+    }
+
+    /// allow single origin, e.g: `https://example.com`
+    pub fn exact(origin: impl Into<ByteStr>) -> Self {
+        Self(Origin::List(vec![origin.into()]))
+    }
+
+    /// allow list of origins
+    pub fn list<I>(origins: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<ByteStr>,
+    {
+        Self(Origin::List(origins.into_iter().map(Into::into).collect()))
+    }
+
+    /// allow origin which the predicate returns `true`
+    pub fn predicate<F>(f: F) -> Self
+    where
+        F: Fn(&str) -> bool + Send + Sync + 'static,
+    {
+        Self(Origin::Predicate(Arc::new(f)))
+    }
+}
+
+impl std::fmt::Debug for AllowOrigin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.0 {
+            Origin::None => f.write_str("None"),
+            Origin::Any => f.write_str("Any"),
+            Origin::List(list) => f.debug_list().entries(list).finish(),
+            Origin::Predicate(_) => f.write_str("Predicate"),
+        }
+    }
+}
+
+// ===== Service =====
+
+/// service returned by [`Cors`] layer
+#[derive(Clone)]
+pub struct CorsService<S> {
+    cors: Cors,
+    inner: S,
+}
+
+impl<S> Service<Request> for CorsService<S>
+where
+    S: HttpService,
+{
+    type Response = Response;
+    type Error = Infallible;
+    type Future = EitherInto<
+        Ready<Result<Response, Infallible>>,
+        CorsFuture<S::Future>,
+        Result<Response, Infallible>,
+    >;
+
+    fn call(&self, req: Request) -> Self::Future {
+        let origin = req.headers().get("origin");
+        if matches!(req.method(), Method::OPTIONS)
+            && origin.is_some()
+            && req.headers().get(REQUEST_METHOD).is_some()
+        {
+            return std::future::ready(Ok(self.cors.preflight(&req))).left_into();
+        }
+
+        let headers = self.cors.headers(origin);
+        CorsFuture { inner: self.inner.call(req), headers: Some(headers) }.right_into()
+    }
+
+    fn methods(&self) -> Option<&[Method]> {
+        self.inner.methods()
+    }
+}
+
+pin_project_lite::pin_project! {
+    /// future returned by [`CorsService`]
+    pub struct CorsFuture<F> {
+        #[pin]
+        inner: F,
+        headers: Option<Vec<(&'static str, HeaderValue)>>,
+    }
+}
+
+impl<F> Future for CorsFuture<F>
+where
+    F: Future<Output = Result<Response, Infallible>>,
+{
+    type Output = Result<Response, Infallible>;
+
+    fn poll(self: std::pin::Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<Self::Output> {
+        let me = self.project();
+        let Ok(mut res) = ready!(me.inner.poll(cx));
+        let headers = res.headers_mut();
+        for (name, value) in me.headers.take().expect("poll after complete") {
+            match name {
+                "vary" => headers.append(name, value),
+                _ => {
+                    headers.insert(name, value);
+                }
+            }
+        }
+        Poll::Ready(Ok(res))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use super::*;
+    use crate::{
+        service::servicefn::service_fn,
+        testing::{call, request},
+    };
+
+    fn inner(called: Arc<AtomicBool>) -> impl HttpService {
+        service_fn(move |_| {
+            called.store(true, Ordering::Relaxed);
+            std::future::ready(Ok::<_, Infallible>(Response::default()))
+        })
+    }
+
+    fn header<'a>(res: &'a Response, name: &str) -> Option<&'a str> {
+        res.headers().get(name).and_then(|value| value.as_str().ok())
+    }
+
+    fn cors() -> Cors {
+        Cors::new()
+            .allow_origin(AllowOrigin::exact("https://example.com"))
+            .allow_methods([Method::GET, Method::POST])
+            .allow_headers(["content-type"])
+            .max_age(Duration::from_secs(600))
+    }
+
+    #[test]
+    fn preflight() {
+        let called = Arc::new(AtomicBool::new(false));
+        let service = cors().layer(inner(called.clone()));
+
+        let res = call(&service, request(Method::OPTIONS, "/", &[
+            ("origin", "https://example.com"),
+            (REQUEST_METHOD, "POST"),
+            (REQUEST_HEADERS, "content-type"),
+        ]));
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+        assert_eq!(header(&res, ALLOW_ORIGIN), Some("https://example.com"));
+        assert_eq!(header(&res, ALLOW_METHODS), Some("GET, POST"));
+        assert_eq!(header(&res, ALLOW_HEADERS), Some("content-type"));
+        assert_eq!(header(&res, MAX_AGE), Some("600"));
+        assert_eq!(
+            header(&res, "vary"),
+            Some("origin, access-control-request-method, access-control-request-headers"),
+        );
+        assert_eq!(header(&res, ALLOW_CREDENTIALS), None);
+
+        // denied origin get no cors headers
+        let res = call(&service, request(Method::OPTIONS, "/", &[
+            ("origin", "https://evil.com"),
+            (REQUEST_METHOD, "POST"),
+        ]));
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+        assert_eq!(header(&res, ALLOW_ORIGIN), None);
+        assert_eq!(header(&res, ALLOW_METHODS), None);
+
+        // preflight never reach the inner service
+        assert!(!called.load(Ordering::Relaxed));
+
+        // `OPTIONS` without `Access-Control-Request-Method` is not a preflight
+        call(&service, request(Method::OPTIONS, "/", &[("origin", "https://example.com")]));
+        assert!(called.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn mirror_preflight() {
+        let service = Cors::permissive().layer(inner(Arc::default()));
+        let res = call(&service, request(Method::OPTIONS, "/", &[
+            ("origin", "https://example.com"),
+            (REQUEST_METHOD, "DELETE"),
+            (REQUEST_HEADERS, "x-token"),
+        ]));
+        assert_eq!(header(&res, ALLOW_ORIGIN), Some("*"));
+        assert_eq!(header(&res, ALLOW_METHODS), Some("DELETE"));
+        assert_eq!(header(&res, ALLOW_HEADERS), Some("x-token"));
+    }
+
+    #[test]
+    fn simple_request() {
+        let service = cors().expose_headers(["x-request-id"]).layer(inner(Arc::default()));
+
+        let res = call(&service, request(Method::GET, "/", &[("origin", "https://example.com")]));
+        assert_eq!(header(&res, ALLOW_ORIGIN), Some("https://example.com"));
+        assert_eq!(header(&res, EXPOSE_HEADERS), Some("x-request-id"));
+        assert_eq!(header(&res, "vary"), Some("origin"));
+        assert_eq!(header(&res, ALLOW_CREDENTIALS), None);
+
+        // response varies by origin even when the origin is denied
+        let res = call(&service, request(Method::GET, "/", &[("origin", "https://evil.com")]));
+        assert_eq!(header(&res, ALLOW_ORIGIN), None);
+        assert_eq!(header(&res, EXPOSE_HEADERS), None);
+        assert_eq!(header(&res, "vary"), Some("origin"));
+
+        // any origin does not vary
+        let service = Cors::permissive().layer(inner(Arc::default()));
+        let res = call(&service, request(Method::GET, "/", &[("origin", "https://example.com")]));
+        assert_eq!(header(&res, ALLOW_ORIGIN), Some("*"));
+        assert_eq!(header(&res, "vary"), None);
+    }
+
+    #[test]
+    fn credentials() {
+        let service = cors().allow_credentials(true).layer(inner(Arc::default()));
+
+        let res = call(&service, request(Method::GET, "/", &[("origin", "https://example.com")]));
+        assert_eq!(header(&res, ALLOW_ORIGIN), Some("https://example.com"));
+        assert_eq!(header(&res, ALLOW_CREDENTIALS), Some("true"));
+
+        let res = call(&service, request(Method::OPTIONS, "/", &[
+            ("origin", "https://example.com"),
+            (REQUEST_METHOD, "GET"),
+        ]));
+        assert_eq!(header(&res, ALLOW_CREDENTIALS), Some("true"));
+
+        let res = call(&service, request(Method::GET, "/", &[("origin", "https://evil.com")]));
+        assert_eq!(header(&res, ALLOW_CREDENTIALS), None);
+    }
+
+    #[test]
+    #[should_panic = "cors credentials cannot be allowed with any origin"]
+    fn credentials_any_origin() {
+        Cors::permissive().allow_credentials(true).layer(inner(Arc::default()));
+    }
+}
@@ -0,0 +1,260 @@
+use std::{convert::Infallible, future::Ready};
+
+use super::encoding::Encoding;
+use crate::{
+    futures::{EitherInto, FutureExt},
+    headers::HeaderValue,
+    helpers::Layer,
+    http::{Method, StatusCode},
+    request::Request,
+    response::{IntoResponse, Response},
+    service::{HttpService, Service},
+};
+
+/// request body decompression [`Layer`]
+///
+/// request with `Content-Encoding` of gzip, deflate, br, or zstd is decompressed when
+/// collected by body extractor like [`Bytes`], [`String`] and [`Json`], the
+/// `Content-Encoding` and `Content-Length` headers are removed
+///
+/// request with unsupported `Content-Encoding` is rejected with
+/// `415 Unsupported Media Type`
+///
+/// the request body [limit][crate::request::Body::limit] applies to the decompressed body,
+/// if no limit is set by [`BodyLimit`], the [`limit`][RequestDecompression::limit] of this
+/// layer is used, so decompression bomb cannot exhaust memory
+///
+/// [`Bytes`]: bytes::Bytes
+/// [`Json`]: crate::helpers::Json
+/// [`BodyLimit`]: crate::helpers::BodyLimit
+#[derive(Debug, Clone, Copy)]
+pub struct RequestDecompression {
+    gzip: bool,
+    deflate: bool,
+    br: bool,
+    zstd: bool,
+    limit: usize,
+}
+
+impl RequestDecompression {
+    /// create new [`RequestDecompression`] with all encoding enabled
+    ///
+    /// the default limit is 2 MiB
+    pub fn new() -> Self {
+        Self { gzip: true, deflate: true, br: true, zstd: true, limit: 2 * 1024 * 1024 }
+    }
+
+    /// enable or disable gzip encoding
+    pub fn gzip(mut self, enable: bool) -> Self {
+        self.gzip = enable;
+        self
+    }
+
+    /// enable or disable deflate encoding
+    pub fn deflate(mut self, enable: bool) -> Self {
+        self.deflate = enable;
+        self
+    }
+
+    /// enable or disable brotli encoding
+    pub fn br(mut self, enable: bool) -> Self {
+        self.br = enable;
+        self
+    }
+
+    /// enable or disable zstd encoding
+    pub fn zstd(mut self, enable: bool) -> Self {
+        self.zstd = enable;
+        self
+    }
+
+    /// decompressed body limit used when request body does not have limit
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    fn is_enabled(&self, encoding: Encoding) -> bool {
+        match encoding {
+            Encoding::Gzip => self.gzip,
+            Encoding::Deflate => self.deflate,
+            Encoding::Br => self.br,
+            Encoding::Zstd => self.zstd,
+        }
+    }
+
+    /// `415 Unsupported Media Type` with `Accept-Encoding` of enabled encodings
+    fn unsupported(&self) -> Response {
+        let accept = [Encoding::Gzip, Encoding::Deflate, Encoding::Br, Encoding::Zstd]
+            .into_iter()
+            .filter(|e| self.is_enabled(*e))
+            .map(Encoding::as_str)
+            .collect::<Vec<_>>();
+        let accept = match accept.is_empty() {
+            true => String::from("identity"),
+            false => accept.join(", "),
+        };
+        let mut res = StatusCode::UNSUPPORTED_MEDIA_TYPE.into_response();
+        res.headers_mut().insert("accept-encoding", HeaderValue::from_string(accept));
+        res
+    }
+}
+
+impl Default for RequestDecompression {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> Layer<S> for RequestDecompression {
+    type Service = RequestDecompressionService<S>;
+
+    fn layer(self, inner: S) -> Self::Service {
+        RequestDecompressionService { decompression: self, inner }
+    }
+}
+
+/// service returned by [`RequestDecompression`] layer
+#[derive(Debug, Clone)]
+pub struct RequestDecompressionService<S> {
+    decompression: RequestDecompression,
+    inner: S,
+}
+
+impl<S> Service<Request> for RequestDecompressionService<S>
+where
+    S: HttpService,
+{
+    type Response = Response;
+    type Error = Infallible;
+    type Future = EitherInto<Ready<Result<Response, Infallible>>, S::Future, Result<Response, Infallible>>;
+
+    fn call(&self, mut req: Request) -> Self::Future {
+        let Some(value) = req.headers().get("content-encoding") else {
+            return self.inner.call(req).right_into();
+        };
+        let value = value.as_str().unwrap_or_default().trim();
+        if value.eq_ignore_ascii_case("identity") {
+            req.headers_mut().remove("content-encoding");
+            return self.inner.call(req).right_into();
+        }
+
+        let encoding = match Encoding::from_str(value) {
+            Some(encoding) if self.decompression.is_enabled(encoding) => encoding,
+            _ => {
+                let res = self.decompression.unsupported();
+                return std::future::ready(Ok(res)).left_into();
+            }
+        };
+
+        let headers = req.headers_mut();
+        headers.remove("content-encoding");
+        headers.remove("content-length");
+
+        let body = req.body_mut();
+        if body.limit().is_none() {
+            body.set_limit(Some(self.decompression.limit));
+        }
+        body.set_encoding(encoding);
+        self.inner.call(req).right_into()
+    }
+
+    fn methods(&self) -> Option<&[Method]> {
+        self.inner.methods()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        helpers::BodyLimit,
+        service::servicefn::service_fn,
+        testing::{body, call, request},
+    };
+
+    /// echo the request headers and body limit seen by the inner service
+    fn inner() -> impl HttpService {
+        service_fn(|req: Request| {
+            let header = |name| req.headers().get(name).map(|e| e.as_str().unwrap().to_owned());
+            let res = format!(
+                "{:?} {:?} {:?}",
+                header("content-encoding"),
+                header("content-length"),
+                req.body().limit(),
+            );
+            std::future::ready(Ok::<_, Infallible>(res.into_response()))
+        })
+    }
+
+    #[test]
+    fn unsupported() {
+        let service = RequestDecompression::new().layer(inner());
+        let res = call(&service, request(Method::POST, "/", &[("content-encoding", "compress")]));
+        assert_eq!(res.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        let accept = res.headers().get("accept-encoding").unwrap().as_str().unwrap();
+        assert_eq!(accept, "gzip, deflate, br, zstd");
+
+        // disabled encoding is not accepted
+        let service = RequestDecompression::new().gzip(false).br(false).layer(inner());
+        let res = call(&service, request(Method::POST, "/", &[("content-encoding", "gzip")]));
+        assert_eq!(res.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        let accept = res.headers().get("accept-encoding").unwrap().as_str().unwrap();
+        assert_eq!(accept, "deflate, zstd");
+    }
+
+    #[test]
+    fn remove_headers() {
+        let headers = [("content-encoding", "gzip"), ("content-length", "10")];
+
+        // the default limit is applied
+        let service = RequestDecompression::new().layer(inner());
+        let res = call(&service, request(Method::POST, "/", &headers));
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(body(res), "None None Some(2097152)");
+
+        // limit set by `BodyLimit` take precedence
+        let service = BodyLimit::new(1024).layer(RequestDecompression::new().limit(64).layer(inner()));
+        let res = call(&service, request(Method::POST, "/", &headers));
+        assert_eq!(body(res), "None None Some(1024)");
+
+        // request without encoding is untouched
+        let res = call(&service, request(Method::POST, "/", &[("content-length", "0")]));
+        assert_eq!(body(res), "None Some(\"0\") Some(1024)");
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn decompressed_limit() {
+        use crate::{Router, post, service::{BoxCloneService, tcp::Config}, testing::serve};
+        use bytes::Bytes;
+
+        let mut encoder = Encoding::Gzip.encoder(crate::middleware::Level::Default);
+        let mut encoded = encoder.encode(&[b'a'; 1000]).unwrap().to_vec();
+        encoded.extend_from_slice(&encoder.finish().unwrap());
+
+        let status_line = |limit: usize| {
+            let router = Router::new()
+                .route("/", post(|body: Bytes| async move { body.len().to_string() }))
+                .layer(RequestDecompression::new().limit(limit));
+            let mut input = format!(
+                "POST / HTTP/1.1\r\ncontent-encoding: gzip\r\ncontent-length: {}\r\n\
+                connection: close\r\n\r\n",
+                encoded.len(),
+            ).into_bytes();
+            input.extend_from_slice(&encoded);
+            let output = serve(BoxCloneService::new(router), Config::new(), &input);
+            let output = String::from_utf8_lossy(&output).into_owned();
+            (output.lines().next().unwrap_or_default().to_owned(), output)
+        };
+
+        // the compressed body itself is within the limit
+        assert!(encoded.len() < 100);
+        let (status, _) = status_line(100);
+        assert_eq!(status, "HTTP/1.1 413 Content Too Large");
+
+        let (status, output) = status_line(1000);
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        assert!(output.ends_with("\r\n\r\n1000"));
+    }
+}
@@ -0,0 +1,140 @@
+//! content coding negotiation
+
+/// supported content coding
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Encoding {
+    Gzip,
+    Deflate,
+    Br,
+    Zstd,
+}
+
+impl Encoding {
+    /// server preference when client accept multiple coding with the same weight
+    pub(crate) const PREFERENCE: [Encoding; 4] = [Encoding::Br, Encoding::Zstd, Encoding::Gzip, Encoding::Deflate];
+
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Encoding::Gzip => "gzip",
+            Encoding::Deflate => "deflate",
+            Encoding::Br => "br",
+            Encoding::Zstd => "zstd",
+        }
+    }
+
+    /// parse content coding, case insensitive
+    pub(crate) fn from_str(value: &str) -> Option<Encoding> {
+        let value = value.trim();
+        if value.eq_ignore_ascii_case("gzip") || value.eq_ignore_ascii_case("x-gzip") {
+            Some(Encoding::Gzip)
+        } else if value.eq_ignore_ascii_case("deflate") {
+            Some(Encoding::Deflate)
+        } else if value.eq_ignore_ascii_case("br") {
+            Some(Encoding::Br)
+        } else if value.eq_ignore_ascii_case("zstd") {
+            Some(Encoding::Zstd)
+        } else {
+            None
+        }
+    }
+
+    /// select coding from `Accept-Encoding` values
+    ///
+    /// returns the enabled coding with the highest weight, coding with zero weight is not
+    /// acceptable, and `*` match any coding not listed
+    pub(crate) fn negotiate<'a>(
+        accept: impl Iterator<Item = &'a str>,
+        enabled: impl Fn(Encoding) -> bool,
+    ) -> Option<Encoding> {
+        // weight in thousandths, `None` if not listed
+        let mut weights = [None::<u16>; 4];
+        let mut wildcard = None;
+
+        for item in accept.flat_map(|value| value.split(',')) {
+            let mut params = item.split(';');
+            let coding = params.next().unwrap_or_default().trim();
+            let weight = params
+                .find_map(|param| {
+                    let (name, value) = param.split_once('=')?;
+                    name.trim().eq_ignore_ascii_case("q").then(|| parse_qvalue(value.trim()))
+                })
+                .unwrap_or(Some(1000));
+            let Some(weight) = weight else {
+                continue;
+            };
+
+            if coding == "*" {
+                wildcard = Some(weight);
+            } else if let Some(encoding) = Encoding::from_str(coding) {
+                weights[encoding as usize] = Some(weight);
+            }
+        }
+
+        let mut selected = None;
+        for encoding in Self::PREFERENCE {
+            if !enabled(encoding) {
+                continue;
+            }
+            let weight = weights[encoding as usize].or(wildcard).unwrap_or(0);
+            if weight > 0 && selected.is_none_or(|(_, w)| weight > w) {
+                selected = Some((encoding, weight));
+            }
+        }
+        selected.map(|(encoding, _)| encoding)
+    }
+}
+
+/// parse `qvalue` into thousandths, `1`, `0.5`, `0.125`
+fn parse_qvalue(value: &str) -> Option<u16> {
+    let (int, frac) = value.split_once('.').unwrap_or((value, ""));
+    if frac.len() > 3 || !frac.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let mut thousandths = 0;
+    for (i, b) in frac.bytes().enumerate() {
+        thousandths += (b - b'0') as u16 * [100, 10, 1][i];
+    }
+    match int {
+        "0" => Some(thousandths),
+        "1" if thousandths == 0 => Some(1000),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn negotiate() {
+        fn select(accept: &str) -> Option<Encoding> {
+            Encoding::negotiate(std::iter::once(accept), |_| true)
+        }
+
+        assert_eq!(select("gzip"), Some(Encoding::Gzip));
+        assert_eq!(select("gzip, deflate, br"), Some(Encoding::Br));
+        assert_eq!(select("gzip;q=1.0, br;q=0.5"), Some(Encoding::Gzip));
+        assert_eq!(select("GZIP; Q=0.8, deflate;q=0.9"), Some(Encoding::Deflate));
+        assert_eq!(select("*"), Some(Encoding::Br));
+        assert_eq!(select("*;q=0.5, br;q=0, zstd;q=0"), Some(Encoding::Gzip));
+        assert_eq!(select("gzip;q=0"), None);
+        assert_eq!(select("identity"), None);
+        assert_eq!(select(""), None);
+        assert_eq!(select("gzip;q=2"), None);
+
+        let gzip_only = Encoding::negotiate(std::iter::once("br, gzip;q=0.1"), |e| e == Encoding::Gzip);
+        assert_eq!(gzip_only, Some(Encoding::Gzip));
+    }
+
+    #[test]
+    fn qvalue() {
+        assert_eq!(parse_qvalue("1"), Some(1000));
+        assert_eq!(parse_qvalue("1.000"), Some(1000));
+        assert_eq!(parse_qvalue("0.5"), Some(500));
+        assert_eq!(parse_qvalue("0.125"), Some(125));
+        assert_eq!(parse_qvalue("0"), Some(0));
+        assert_eq!(parse_qvalue("1.5"), None);
+        assert_eq!(parse_qvalue("0.1234"), None);
+        assert_eq!(parse_qvalue("abc"), None);
+    }
+}
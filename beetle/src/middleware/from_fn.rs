@@ -0,0 +1,209 @@
+use std::{convert::Infallible, sync::Arc};
+
+use crate::{
+    futures::{FutureExt, Map},
+    helpers::Layer,
+    http::Method,
+    request::Request,
+    response::{IntoResponse, Response},
+    service::{BoxCloneService, HttpService, Service, boxed::BoxFuture},
+};
+
+/// create middleware [`Layer`] from async function
+///
+/// the function receive the request and [`Next`] which calls the inner service, and returns
+/// any [`IntoResponse`]
+///
+/// ```
+/// use beetle::{Request, Response, Router, middleware::{from_fn, Next}};
+///
+/// async fn auth(req: Request, next: Next) -> Result<Response, &'static str> {
+///     match req.headers().get("authorization") {
+///         Some(_) => Ok(next.run(req).await),
+///         None => Err("unauthorized"),
+///     }
+/// }
+///
+/// let router = Router::new().layer(from_fn(auth));
+/// ```
+pub fn from_fn<F>(f: F) -> FromFnLayer<F> {
+    FromFnLayer { f }
+}
+
+/// [`Layer`] returned from [`from_fn`]
+#[derive(Clone)]
+pub struct FromFnLayer<F> {
+    f: F,
+}
+
+impl<F, S> Layer<S> for FromFnLayer<F>
+where
+    S: HttpService,
+{
+    type Service = FromFn<F>;
+
+    fn layer(self, inner: S) -> Self::Service {
+        FromFn { f: Arc::new(self.f), next: BoxCloneService::new(inner) }
+    }
+}
+
+/// middleware service returned from [`FromFnLayer`]
+pub struct FromFn<F> {
+    f: Arc<F>,
+    next: BoxCloneService,
+}
+
+impl<F, Fut> Service<Request> for FromFn<F>
+where
+    F: Fn(Request, Next) -> Fut,
+    Fut: Future,
+    Fut::Output: IntoResponse,
+{
+    type Response = Response;
+    type Error = Infallible;
+    type Future = Map<Fut, fn(Fut::Output) -> Result<Response, Infallible>>;
+
+    fn call(&self, req: Request) -> Self::Future {
+        let next = Next { inner: self.next.clone() };
+        (self.f)(req, next).map(|res| Ok(res.into_response()))
+    }
+
+    fn methods(&self) -> Option<&[Method]> {
+        self.next.methods()
+    }
+}
+
+/// the remaining middleware and handler of [`from_fn`] middleware
+#[derive(Clone)]
+pub struct Next {
+    inner: BoxCloneService,
+}
+
+impl Next {
+    /// calls the inner service
+    pub fn run(self, req: Request) -> NextFuture {
+        NextFuture { inner: self.inner.call(req) }
+    }
+}
+
+impl std::fmt::Debug for Next {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Next").finish_non_exhaustive()
+    }
+}
+
+/// future returned from [`Next::run`]
+pub struct NextFuture {
+    inner: BoxFuture,
+}
+
+impl Future for NextFuture {
+    type Output = Response;
+
+    fn poll(mut self: std::pin::Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> std::task::Poll<Self::Output> {
+        self.inner.as_mut().poll(cx).map(|Ok(res)| res)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use super::*;
+    use crate::{
+        headers::HeaderValue,
+        http::{Method, StatusCode},
+        service::servicefn::service_fn,
+        testing::{body, call, request},
+    };
+
+    /// echo the `x-user` request header
+    fn inner(called: Arc<AtomicBool>) -> impl HttpService {
+        service_fn(move |req: Request| {
+            called.store(true, Ordering::Relaxed);
+            let user = req.headers().get("x-user").map(|value| value.as_bytes().to_vec());
+            let response = String::from_utf8(user.unwrap_or_default()).unwrap().into_response();
+            std::future::ready(Ok::<_, Infallible>(response))
+        })
+    }
+
+    #[test]
+    fn short_circuit() {
+        async fn auth(req: Request, next: Next) -> Result<Response, (StatusCode, String)> {
+            match req.headers().get("authorization") {
+                Some(_) => Ok(next.run(req).await),
+                None => Err((StatusCode::UNAUTHORIZED, String::from("unauthorized"))),
+            }
+        }
+
+        let called = Arc::new(AtomicBool::new(false));
+        let service = from_fn(auth).layer(inner(called.clone()));
+
+        let response = call(&service, request(Method::GET, "/", &[]));
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(body(response), "unauthorized");
+        assert!(!called.load(Ordering::Relaxed));
+
+        let response = call(&service, request(Method::GET, "/", &[("authorization", "a")]));
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(called.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn map_request() {
+        async fn user(mut req: Request, next: Next) -> Response {
+            req.headers_mut().insert("x-user", HeaderValue::from_static("alice"));
+            next.run(req).await
+        }
+
+        let service = from_fn(user).layer(inner(Arc::default()));
+        let response = call(&service, request(Method::GET, "/", &[]));
+        assert_eq!(body(response), "alice");
+    }
+
+    #[test]
+    fn map_response() {
+        async fn header(req: Request, next: Next) -> Response {
+            let mut response = next.run(req).await;
+            response.headers_mut().insert("x-powered-by", HeaderValue::from_static("beetle"));
+            *response.status_mut() = StatusCode::CREATED;
+            response
+        }
+
+        let service = from_fn(header).layer(inner(Arc::default()));
+        let response = call(&service, request(Method::GET, "/", &[("x-user", "bob")]));
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(response.headers().get("x-powered-by").map(HeaderValue::as_bytes), Some(&b"beetle"[..]));
+        assert_eq!(body(response), "bob");
+    }
+
+    async fn passthrough(req: Request, next: Next) -> Response {
+        next.run(req).await
+    }
+
+    #[test]
+    fn inner_methods() {
+        use crate::{Router, get};
+
+        async fn handler() { }
+
+        let service = from_fn(passthrough).layer(get(handler).post(handler));
+        assert_eq!(Service::methods(&service), Some(&[Method::GET, Method::POST][..]));
+
+        let router = Router::new().route("/users", from_fn(passthrough).layer(get(handler)));
+        let routes: Vec<_> = router.routes().map(|(method, path)| (method.cloned(), path.to_owned())).collect();
+        assert_eq!(routes, [(Some(Method::GET), "/users".to_owned())]);
+    }
+
+    #[test]
+    #[should_panic = "conflicting route `POST /users` with `GET /users`"]
+    fn inner_methods_conflict() {
+        use crate::{Router, get, post};
+
+        async fn handler() { }
+
+        let _ = Router::new()
+            .route("/users", from_fn(passthrough).layer(get(handler)))
+            .route("/users", post(handler));
+    }
+}
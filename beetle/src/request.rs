@@ -3,15 +3,17 @@ use crate::{
     IntoResponse,
     common::ByteStr,
     headers::HeaderMap,
-    http::{Extensions, Method, Version},
+    http::{Extensions, Method, Uri, Version},
 };
 
 mod body;
+mod chunked;
 mod parts;
 
 pub mod futures;
 
-pub use body::{Body, Collect};
+pub use body::{Body, BodyError, Collect, LengthLimitError};
+pub(crate) use body::{Coding, Reader, Timeout};
 pub use parts::Parts;
 
 /// A type that can be constructed from [`Request`].
@@ -59,6 +61,16 @@ impl Request {
         (self.parts, self.body)
     }
 
+    /// Returns the request [`Body`].
+    pub fn body(&self) -> &Body {
+        &self.body
+    }
+
+    /// Returns mutable reference to the request [`Body`].
+    pub fn body_mut(&mut self) -> &mut Body {
+        &mut self.body
+    }
+
     /// Comsume request into [`Body`].
     pub fn into_body(self) -> Body {
         self.body
@@ -76,15 +88,30 @@ impl Request {
 /// Delegate methods.
 impl Request {
     /// Returns HTTP Method.
-    pub fn method(&self) -> Method {
+    pub fn method(&self) -> &Method {
         self.parts.method()
     }
 
+    /// Returns HTTP Uri.
+    pub fn uri(&self) -> &Uri {
+        self.parts.uri()
+    }
+
+    /// Returns mutable reference to HTTP Uri.
+    pub fn uri_mut(&mut self) -> &mut Uri {
+        self.parts.uri_mut()
+    }
+
     /// Returns HTTP Path.
     pub fn path(&self) -> &ByteStr {
         self.parts.path()
     }
 
+    /// Returns HTTP Query string, without the leading `?`.
+    pub fn query(&self) -> Option<&str> {
+        self.parts.query()
+    }
+
     /// Returns HTTP Version.
     pub fn version(&self) -> Version {
         self.parts.version()
@@ -94,13 +121,18 @@ impl Request {
     pub fn headers(&self) -> &HeaderMap {
         self.parts.headers()
     }
+
+    /// Returns mutable reference to HTTP Headers.
+    pub fn headers_mut(&mut self) -> &mut HeaderMap {
+        self.parts.headers_mut()
+    }
 }
 
 impl std::fmt::Debug for Request {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Request")
             .field("method", &self.parts.method())
-            .field("path", &self.parts.path())
+            .field("uri", &self.parts.uri())
             .field("version", &self.parts.version())
             .field("headers", &self.parts.headers())
             .finish()
@@ -1,113 +1,403 @@
-use bytes::{BufMut, Bytes, BytesMut};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
 use std::{
     io,
     pin::Pin,
-    sync::{atomic::{AtomicUsize, Ordering}, Arc},
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
     task::{ready, Context, Poll},
+    time::Duration,
 };
 
+use super::chunked::ChunkedDecoder;
 use crate::{io::StreamReadExt, net::Socket};
 
-fn exhausted() -> io::Error {
-    io::Error::new(io::ErrorKind::QuotaExceeded, "request body exhausted")
+#[cfg(feature = "compression")]
+use crate::middleware::encoding::Encoding;
+
+fn unexpected_eof() -> io::Error {
+    io::Error::new(io::ErrorKind::UnexpectedEof, "connection closed before request body complete")
 }
 
-#[derive(Debug)]
-pub struct Body {
-    content_len: usize,
-    io: Option<Arc<Socket>>,
+/// Maximum bytes read from io to drain the rest of the body, larger body is not worth to
+/// drain and the connection is closed instead.
+const MAX_DRAIN: usize = 64 * 1024;
 
-    /// How many content is read, including `self.buffer`
-    /// and incoming bytes from `self.io`.
-    read: AtomicUsize,
+fn length_limit(limit: usize) -> BodyError {
+    BodyError::LengthLimit(LengthLimitError { limit })
+}
 
-    /// May contains partially read body
-    ///
-    /// There is maybe already partially read body when parsing headers.
-    /// If so, then this bytes contains it.
-    ///
-    /// We need to track read externally so no need mut reference,
-    /// `self.read` also count `self.buffer` read.
-    buffer: Bytes,
+fn timed_out() -> io::Error {
+    io::Error::new(io::ErrorKind::TimedOut, "timeout reading request body")
 }
 
-impl Body {
-    /// Create empty [`Body`].
-    pub fn empty() -> Self {
+type BoxSleep = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// Timeout for each read of the request body.
+pub(crate) struct Timeout {
+    new: Box<dyn Fn() -> BoxSleep + Send>,
+    sleep: Option<BoxSleep>,
+    elapsed: bool,
+}
+
+impl Timeout {
+    pub(crate) fn new<T>(duration: Duration, sleep: fn(Duration) -> T) -> Self
+    where
+        T: Future<Output = ()> + Send + 'static,
+    {
         Self {
-            content_len: 0,
-            io: None,
-            read: AtomicUsize::new(0),
-            buffer: Bytes::new()
+            new: Box::new(move || Box::pin(sleep(duration))),
+            sleep: None,
+            elapsed: false,
         }
     }
+}
+
+impl std::fmt::Debug for Timeout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Timeout").field("elapsed", &self.elapsed).finish()
+    }
+}
+
+/// Read from io, the timeout is restarted on every successful read.
+fn poll_read_timeout<B: BufMut>(
+    io: &Socket,
+    timeout: Option<&mut Timeout>,
+    cx: &mut Context,
+    buf: &mut B,
+) -> Poll<io::Result<usize>> {
+    let Some(timeout) = timeout else {
+        return io.poll_read_buf(cx, buf);
+    };
+
+    if let Poll::Ready(result) = io.poll_read_buf(cx, buf) {
+        timeout.sleep = None;
+        return Poll::Ready(result);
+    }
 
+    let sleep = timeout.sleep.get_or_insert_with(&timeout.new);
+    ready!(sleep.as_mut().poll(cx));
+    timeout.elapsed = true;
+    Poll::Ready(Err(timed_out()))
+}
+
+/// Request body framing.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Coding {
+    /// Body length is known from `Content-Length`.
+    Length(usize),
+    /// Body is sent with `Transfer-Encoding: chunked`.
+    Chunked,
+}
+
+/// Body reading state, shared between [`Body`] and the connection.
+///
+/// Bytes that is read from io but does not belong to the body is kept in `buffer`, the
+/// connection then continue to parse it as the next request.
+#[derive(Debug)]
+pub(crate) struct Reader {
+    io: Arc<Socket>,
+    state: ReadState,
+    timeout: Option<Timeout>,
+    /// Body exceeds the limit and is abandoned.
+    exceeded: bool,
+    /// Bytes read from io while draining the body.
+    drained: usize,
+    /// Bytes read from io that is not yet consumed.
+    ///
+    /// For chunked body, this is still chunk encoded.
+    buffer: BytesMut,
+}
+
+#[derive(Debug)]
+enum ReadState {
+    /// Remaining content length.
+    Length(usize),
+    Chunked(ChunkedDecoder),
+    Done,
+}
+
+impl Reader {
+    /// `buffer` is the bytes after request head, which maybe contains partially read body.
     pub(crate) fn new(
-        content_len: usize,
-        io: Option<Arc<Socket>>,
-        buffer: Bytes,
+        coding: Coding,
+        io: Arc<Socket>,
+        buffer: BytesMut,
+        timeout: Option<Timeout>,
     ) -> Self {
         Self {
-            content_len,
             io,
-            read: AtomicUsize::new(buffer.len()),
+            timeout,
+            exceeded: false,
+            drained: 0,
+            state: match coding {
+                Coding::Length(0) => ReadState::Done,
+                Coding::Length(len) => ReadState::Length(len),
+                Coding::Chunked => ReadState::Chunked(ChunkedDecoder::default()),
+            },
             buffer,
         }
     }
 
-    /// Returns maybe partially read body.
+    /// Returns `true` if reading body is timed out.
+    pub(crate) fn is_timed_out(&self) -> bool {
+        self.timeout.as_ref().is_some_and(|timeout| timeout.elapsed)
+    }
+
+    /// Returns `true` if body exceeds the limit, the rest of the body is not worth to drain.
+    pub(crate) fn is_exceeded(&self) -> bool {
+        self.exceeded
+    }
+
+    /// Returns `false` if the rest of the body is known to be too large to drain.
+    pub(crate) fn is_drainable(&self) -> bool {
+        !matches!(self.state, ReadState::Length(remaining) if remaining > MAX_DRAIN)
+    }
+
+    /// Remaining content to be read, or [`None`] if body is chunked.
+    fn remaining(&self) -> Option<usize> {
+        match &self.state {
+            ReadState::Length(remaining) => Some(*remaining),
+            ReadState::Chunked(_) => None,
+            ReadState::Done => Some(0),
+        }
+    }
+
+    /// Read the rest of the body into `dst`, until `dst` length exceeds `limit`.
     ///
-    /// There is maybe already partially read body when parsing headers.
-    /// If so, then this bytes contains it.
+    /// Content-Length body never read past its length from io.
+    fn poll_collect(
+        &mut self,
+        cx: &mut Context,
+        dst: &mut BytesMut,
+        limit: usize,
+    ) -> Poll<Result<(), BodyError>> {
+        if let ReadState::Length(remaining) = self.state
+            && remaining.saturating_add(dst.len()) > limit
+        {
+            self.exceeded = true;
+            return Poll::Ready(Err(length_limit(limit)));
+        }
+
+        loop {
+            match &mut self.state {
+                ReadState::Length(remaining) => {
+                    let n = (*remaining).min(self.buffer.len());
+                    dst.unsplit(self.buffer.split_to(n));
+                    *remaining -= n;
+
+                    if *remaining == 0 {
+                        self.state = ReadState::Done;
+                        continue;
+                    }
+
+                    let limit = *remaining;
+                    self.buffer.reserve(limit.min(8 * 1024));
+                    let mut buf = (&mut self.buffer).limit(limit);
+                    if ready!(poll_read_timeout(&self.io, self.timeout.as_mut(), cx, &mut buf)?) == 0 {
+                        return Poll::Ready(Err(unexpected_eof().into()));
+                    }
+                }
+                ReadState::Chunked(decoder) => {
+                    let done = decoder.decode(&mut self.buffer, dst)?;
+
+                    if dst.len() > limit {
+                        self.exceeded = true;
+                        return Poll::Ready(Err(length_limit(limit)));
+                    }
+
+                    if done {
+                        self.state = ReadState::Done;
+                        continue;
+                    }
+
+                    self.buffer.reserve(1024);
+                    let buf = &mut self.buffer;
+                    if ready!(poll_read_timeout(&self.io, self.timeout.as_mut(), cx, buf)?) == 0 {
+                        return Poll::Ready(Err(unexpected_eof().into()));
+                    }
+                }
+                ReadState::Done => return Poll::Ready(Ok(())),
+            }
+        }
+    }
+
+    /// Discard the rest of the body, so the next request can be read.
     ///
-    /// No cloning is done to ensure uniqueness.
+    /// Returns `false` if draining requires reading more than [`MAX_DRAIN`] bytes from io, the
+    /// connection should be closed instead.
+    pub(crate) fn poll_drain(&mut self, cx: &mut Context) -> Poll<io::Result<bool>> {
+        loop {
+            match &mut self.state {
+                ReadState::Length(remaining) => {
+                    let n = (*remaining).min(self.buffer.len());
+                    self.buffer.advance(n);
+                    *remaining -= n;
+
+                    if *remaining == 0 {
+                        self.state = ReadState::Done;
+                        continue;
+                    }
+
+                    if self.drained.saturating_add(*remaining) > MAX_DRAIN {
+                        return Poll::Ready(Ok(false));
+                    }
+
+                    let limit = *remaining;
+                    self.buffer.reserve(limit.min(8 * 1024));
+                    let mut buf = (&mut self.buffer).limit(limit);
+                    match ready!(poll_read_timeout(&self.io, self.timeout.as_mut(), cx, &mut buf)?) {
+                        0 => return Poll::Ready(Err(unexpected_eof())),
+                        n => self.drained += n,
+                    }
+                }
+                ReadState::Chunked(decoder) => {
+                    if decoder.skip(&mut self.buffer)? {
+                        self.state = ReadState::Done;
+                        continue;
+                    }
+
+                    if self.drained > MAX_DRAIN {
+                        return Poll::Ready(Ok(false));
+                    }
+
+                    self.buffer.reserve(8 * 1024);
+                    let buf = &mut self.buffer;
+                    match ready!(poll_read_timeout(&self.io, self.timeout.as_mut(), cx, buf)?) {
+                        0 => return Poll::Ready(Err(unexpected_eof())),
+                        n => self.drained += n,
+                    }
+                }
+                ReadState::Done => return Poll::Ready(Ok(true)),
+            }
+        }
+    }
+
+    /// Take bytes that is read after the body.
     ///
-    /// Note that subsequent read will not be written to this buffer.
-    pub(crate) fn take_buffer(&mut self) -> Bytes {
-        std::mem::take(&mut self.buffer)
+    /// This should only be called after the body is drained.
+    pub(crate) fn take_buffer(&mut self) -> BytesMut {
+        debug_assert!(matches!(self.state, ReadState::Done));
+        self.buffer.split()
+    }
+}
+
+fn lock(reader: &Mutex<Reader>) -> MutexGuard<'_, Reader> {
+    reader.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[derive(Debug)]
+pub struct Body {
+    coding: Coding,
+    limit: Option<usize>,
+    reader: Option<Arc<Mutex<Reader>>>,
+    /// `Content-Encoding` to be decoded when collected.
+    #[cfg(feature = "compression")]
+    encoding: Option<Encoding>,
+}
+
+impl Body {
+    /// Create empty [`Body`].
+    pub fn empty() -> Self {
+        Self {
+            coding: Coding::Length(0),
+            limit: None,
+            reader: None,
+            #[cfg(feature = "compression")]
+            encoding: None,
+        }
+    }
+
+    pub(crate) fn new(coding: Coding, reader: Arc<Mutex<Reader>>) -> Self {
+        Self {
+            coding,
+            limit: None,
+            reader: Some(reader),
+            #[cfg(feature = "compression")]
+            encoding: None,
+        }
     }
 
     /// Read all body and returns it as [`BytesMut`].
+    ///
+    /// Chunked body is decoded while collected. If body exceeds the [limit][Body::limit],
+    /// collect returns [`BodyError::LengthLimit`].
+    ///
+    /// Body that is marked by [`RequestDecompression`] is decompressed, the limit applies to
+    /// both the compressed and decompressed body.
+    ///
+    /// [`RequestDecompression`]: crate::middleware::RequestDecompression
     pub fn collect(self) -> Collect {
         Collect {
-            buffer: match self.buffer.try_into_mut() {
-                Ok(ok) => ok,
-                Err(ok) => ok.into(),
-            },
-            content_len: self.content_len,
-            read: self.read.load(Ordering::Relaxed),
-            io: self.io,
+            buffer: BytesMut::new(),
+            limit: self.limit.unwrap_or(usize::MAX),
+            reader: self.reader,
+            #[cfg(feature = "compression")]
+            encoding: self.encoding,
         }
     }
 
-    /// Remaining content to be read.
-    pub fn remaining(&self) -> usize {
-        self.content_len - self.read.load(Ordering::Relaxed)
+    /// Decode body with given `Content-Encoding` when collected.
+    #[cfg(feature = "compression")]
+    pub(crate) fn set_encoding(&mut self, encoding: Encoding) {
+        self.encoding = Some(encoding);
     }
 
-    /// Returns `true` if there is still more content to be read.
-    pub fn is_remaining(&self) -> bool {
-        self.remaining() != 0
+    /// Returns the maximum body length that can be collected, or [`None`] if unlimited.
+    pub fn limit(&self) -> Option<usize> {
+        self.limit
     }
 
-    /// Poll read from underlying io.
+    /// Set the maximum body length that can be collected.
     ///
-    /// Note that there is maybe already partially read body when parsing headers.
-    /// To access it, use [`take_buffer`][Self::take_buffer].
-    ///
-    /// # Errors
+    /// Declared `Content-Length` larger than the limit is rejected before any read, while
+    /// chunked body is rejected once the collected body exceeds the limit.
+    pub fn set_limit(&mut self, limit: Option<usize>) {
+        self.limit = limit;
+    }
+
+    /// Returns `true` if declared `Content-Length` is larger than the limit.
+    pub fn is_exceeded(&self) -> bool {
+        matches!((self.coding, self.limit), (Coding::Length(len), Some(limit)) if len > limit)
+    }
+
+    /// Reject body that exceeds the limit without reading it.
     ///
-    /// If the underlying io is exhausted, an error is returned.
-    pub(crate) fn poll_read_buf<B: BufMut>(
-        &self,
-        cx: &mut Context,
-        buf: &mut B,
-    ) -> Poll<io::Result<usize>> {
-        match &self.io {
-            Some(io) => io.poll_read_buf(cx, buf),
-            None => Poll::Ready(Err(exhausted())),
+    /// The connection will be closed instead of draining the body.
+    pub(crate) fn reject(&self) -> LengthLimitError {
+        if let Some(reader) = &self.reader {
+            lock(reader).exceeded = true;
+        }
+        LengthLimitError {
+            limit: self.limit.unwrap_or_default(),
         }
     }
+
+    /// Returns the `Content-Length`, or [`None`] if body is chunked.
+    pub fn content_len(&self) -> Option<usize> {
+        match self.coding {
+            Coding::Length(len) => Some(len),
+            Coding::Chunked => None,
+        }
+    }
+
+    /// Returns `true` if body is sent with `Transfer-Encoding: chunked`.
+    pub fn is_chunked(&self) -> bool {
+        matches!(self.coding, Coding::Chunked)
+    }
+
+    /// Remaining content to be read, or [`None`] if body is chunked.
+    pub fn remaining(&self) -> Option<usize> {
+        match &self.reader {
+            Some(reader) => lock(reader).remaining(),
+            None => Some(0),
+        }
+    }
+
+    /// Returns `true` if there is still more content to be read.
+    ///
+    /// Chunked body is always considered remaining.
+    pub fn is_remaining(&self) -> bool {
+        self.remaining() != Some(0)
+    }
 }
 
 impl Default for Body {
@@ -119,42 +409,112 @@ impl Default for Body {
 #[derive(Debug)]
 pub struct Collect {
     buffer: BytesMut,
-    content_len: usize,
-    read: usize,
-    io: Option<Arc<Socket>>,
+    limit: usize,
+    reader: Option<Arc<Mutex<Reader>>>,
+    #[cfg(feature = "compression")]
+    encoding: Option<Encoding>,
 }
 
 impl Collect {
-    pub fn remaining(&self) -> usize {
-        self.content_len - self.read
+    /// Remaining content to be read, or [`None`] if body is chunked.
+    pub fn remaining(&self) -> Option<usize> {
+        match &self.reader {
+            Some(reader) => lock(reader).remaining(),
+            None => Some(0),
+        }
     }
 
     /// Returns `true` if there is still more content to be read.
+    ///
+    /// Chunked body is always considered remaining until collected.
     pub fn is_remaining(&self) -> bool {
-        self.remaining() != 0
+        self.remaining() != Some(0)
     }
 }
 
 impl Future for Collect {
-    type Output = io::Result<Bytes>;
+    type Output = Result<Bytes, BodyError>;
 
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         let me = self.get_mut();
 
-        let Some(io) = &me.io else {
-            return if me.buffer.is_empty() {
-                Poll::Ready(Err(exhausted()))
-            } else {
-                Poll::Ready(Ok(std::mem::take(&mut me.buffer).freeze()))
-            };
-        };
+        if let Some(reader) = &me.reader {
+            ready!(lock(reader).poll_collect(cx, &mut me.buffer, me.limit)?);
+        }
+
+        let buffer = std::mem::take(&mut me.buffer).freeze();
 
-        while me.content_len - me.read != 0 {
-            me.read += ready!(io.poll_read_buf(cx, &mut me.buffer)?);
-            // TODO: guard against overflow body,
-            // will it contains subsequent request ?
+        #[cfg(feature = "compression")]
+        if let Some(encoding) = me.encoding {
+            return Poll::Ready(match encoding.decode(&buffer, me.limit) {
+                Ok(Some(decoded)) => Ok(decoded),
+                Ok(None) => Err(length_limit(me.limit)),
+                Err(err) => Err(io::Error::new(io::ErrorKind::InvalidData, err).into()),
+            });
         }
 
-        Poll::Ready(Ok(std::mem::take(&mut me.buffer).freeze()))
+        Poll::Ready(Ok(buffer))
+    }
+}
+
+// ===== Error =====
+
+/// An error when collecting request body.
+#[derive(Debug)]
+pub enum BodyError {
+    /// Failed to read body from io.
+    Io(io::Error),
+    /// Body exceeds the limit.
+    LengthLimit(LengthLimitError),
+}
+
+/// Request body exceeds the limit.
+///
+/// Implement [`IntoResponse`] with `413 Content Too Large`.
+///
+/// [`IntoResponse`]: crate::IntoResponse
+#[derive(Debug)]
+pub struct LengthLimitError {
+    limit: usize,
+}
+
+impl LengthLimitError {
+    /// Returns the exceeded limit.
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+}
+
+impl From<io::Error> for BodyError {
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl From<BodyError> for io::Error {
+    fn from(value: BodyError) -> Self {
+        match value {
+            BodyError::Io(error) => error,
+            BodyError::LengthLimit(error) => io::Error::new(io::ErrorKind::InvalidData, error),
+        }
+    }
+}
+
+impl std::error::Error for BodyError {}
+
+impl std::fmt::Display for BodyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BodyError::Io(error) => error.fmt(f),
+            BodyError::LengthLimit(error) => error.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for LengthLimitError {}
+
+impl std::fmt::Display for LengthLimitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "request body exceeds the limit of {} bytes", self.limit)
     }
 }
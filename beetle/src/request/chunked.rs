@@ -0,0 +1,230 @@
+//! chunked transfer coding decoder
+use bytes::{Buf, BytesMut};
+use memchr::memmem;
+use std::io;
+
+/// maximum length of chunk size line, including extensions
+const MAX_LINE: usize = 4096;
+
+/// maximum length of all trailer fields
+const MAX_TRAILERS: usize = 8192;
+
+fn malformed(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// incremental chunked body decoder
+///
+/// <https://www.rfc-editor.org/rfc/rfc9112#name-chunked-transfer-coding>
+#[derive(Debug, Default)]
+pub(crate) struct ChunkedDecoder {
+    state: State,
+    trailers: usize,
+}
+
+#[derive(Debug, Default)]
+enum State {
+    #[default]
+    Size,
+    Data(u64),
+    DataEnd,
+    Trailer,
+    Done,
+}
+
+impl ChunkedDecoder {
+    /// decode chunks from `src` into `dst`
+    ///
+    /// decoded bytes are consumed from `src`, any bytes after the last chunk is left untouched
+    ///
+    /// returns `true` if the last chunk and trailers are decoded, or `false` if more bytes are
+    /// required
+    pub fn decode(&mut self, src: &mut BytesMut, dst: &mut BytesMut) -> io::Result<bool> {
+        self.decode_into(src, Some(dst))
+    }
+
+    /// same as [`decode`][Self::decode], but chunk data is discarded
+    pub fn skip(&mut self, src: &mut BytesMut) -> io::Result<bool> {
+        self.decode_into(src, None)
+    }
+
+    fn decode_into(&mut self, src: &mut BytesMut, mut dst: Option<&mut BytesMut>) -> io::Result<bool> {
+        loop {
+            match &mut self.state {
+                State::Size => {
+                    let Some(line) = take_line(src, MAX_LINE)? else {
+                        return Ok(false);
+                    };
+                    let size = parse_size(&line)?;
+                    self.state = match size {
+                        0 => State::Trailer,
+                        size => State::Data(size),
+                    };
+                }
+                State::Data(remaining) => {
+                    if src.is_empty() {
+                        return Ok(false);
+                    }
+                    let len = (*remaining).min(src.len() as u64) as usize;
+                    if let Some(dst) = &mut dst {
+                        dst.extend_from_slice(&src[..len]);
+                    }
+                    src.advance(len);
+                    *remaining -= len as u64;
+                    if *remaining == 0 {
+                        self.state = State::DataEnd;
+                    }
+                }
+                State::DataEnd => {
+                    if src.len() < 2 {
+                        return Ok(false);
+                    }
+                    if &src[..2] != b"\r\n" {
+                        return Err(malformed("missing CRLF after chunk data"));
+                    }
+                    src.advance(2);
+                    self.state = State::Size;
+                }
+                State::Trailer => {
+                    let Some(line) = take_line(src, MAX_TRAILERS.saturating_sub(self.trailers))? else {
+                        return Ok(false);
+                    };
+                    if line.is_empty() {
+                        self.state = State::Done;
+                        continue;
+                    }
+                    if self.trailers + line.len() + 2 > MAX_TRAILERS {
+                        return Err(malformed("chunked trailers too large"));
+                    }
+                    // trailer fields are validated, but not used
+                    self.trailers += line.len() + 2;
+                    match line.iter().position(|&b| b == b':') {
+                        Some(0) | None => return Err(malformed("invalid chunked trailer field")),
+                        Some(_) => {}
+                    }
+                }
+                State::Done => return Ok(true),
+            }
+        }
+    }
+}
+
+/// take a CRLF terminated line, without the CRLF
+fn take_line(src: &mut BytesMut, max: usize) -> io::Result<Option<BytesMut>> {
+    match memmem::find(src, b"\r\n") {
+        Some(n) if n > max => Err(malformed("chunked line too long")),
+        Some(n) => {
+            let line = src.split_to(n);
+            src.advance(2);
+            Ok(Some(line))
+        }
+        None if src.len() > max => Err(malformed("chunked line too long")),
+        None => Ok(None),
+    }
+}
+
+/// parse `chunk-size [ chunk-ext ]`
+fn parse_size(line: &[u8]) -> io::Result<u64> {
+    let end = line.iter().position(|b| !b.is_ascii_hexdigit()).unwrap_or(line.len());
+    let (size, ext) = line.split_at(end);
+
+    if size.is_empty() || size.len() > 16 {
+        return Err(malformed("invalid chunk size"));
+    }
+
+    let mut value = 0u64;
+    for &b in size {
+        value = value << 4 | (b as char).to_digit(16).unwrap() as u64;
+    }
+
+    // chunk-ext = *( BWS ";" BWS chunk-ext-name [ BWS "=" BWS chunk-ext-val ] )
+    let ext = ext.trim_ascii_start();
+    if !ext.is_empty() && (ext[0] != b';' || ext.iter().any(|&b| b.is_ascii_control() && b != b'\t')) {
+        return Err(malformed("invalid chunk extension"));
+    }
+
+    Ok(value)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn decode(input: &[u8]) -> io::Result<(bool, BytesMut, BytesMut)> {
+        let mut src = BytesMut::from(input);
+        let mut dst = BytesMut::new();
+        let done = ChunkedDecoder::default().decode(&mut src, &mut dst)?;
+        Ok((done, dst, src))
+    }
+
+    #[test]
+    fn chunked() {
+        let (done, body, rest) = decode(b"4\r\nWiki\r\n5;ext=1\r\npedia\r\n0\r\n\r\nGET").unwrap();
+        assert!(done);
+        assert_eq!(&body[..], b"Wikipedia");
+        assert_eq!(&rest[..], b"GET");
+
+        let (done, body, _) = decode(b"A \t; a; b=\"c\"\r\n0123456789\r\n0\r\nExpires: never\r\n\r\n").unwrap();
+        assert!(done);
+        assert_eq!(&body[..], b"0123456789");
+    }
+
+    #[test]
+    fn skip() {
+        let mut decoder = ChunkedDecoder::default();
+        let mut src = BytesMut::from(&b"4\r\nWiki\r\n5\r\npedia\r\n0\r\n\r\nGET"[..]);
+        assert!(decoder.skip(&mut src).unwrap());
+        assert_eq!(&src[..], b"GET");
+    }
+
+    #[test]
+    fn partial() {
+        let mut decoder = ChunkedDecoder::default();
+        let mut src = BytesMut::new();
+        let mut dst = BytesMut::new();
+
+        for &b in b"3\r\nabc\r\n0\r\n\r\n" {
+            assert!(!matches!(decoder.state, State::Done));
+            src.extend_from_slice(&[b]);
+            decoder.decode(&mut src, &mut dst).unwrap();
+        }
+
+        assert!(decoder.decode(&mut src, &mut dst).unwrap());
+        assert_eq!(&dst[..], b"abc");
+    }
+
+    #[test]
+    fn malformed_framing() {
+        assert!(decode(b"x\r\n").is_err());
+        assert!(decode(b"\r\n").is_err());
+        assert!(decode(b"3\r\nabcd\r\n").is_err());
+        assert!(decode(b"3 x\r\nabc\r\n").is_err());
+        assert!(decode(b"11111111111111111\r\n").is_err());
+        assert!(decode(b"0\r\ninvalid\r\n\r\n").is_err());
+    }
+
+    #[test]
+    fn trailers_limit() {
+        // each field is 1024 bytes including CRLF
+        let field = format!("x:{}\r\n", "a".repeat(1020));
+        let trailers = |n: usize, extra: &str| {
+            let mut input = String::from("0\r\n");
+            input.push_str(&field.repeat(n));
+            input.push_str(extra);
+            input.push_str("\r\n");
+            input
+        };
+
+        let (done, ..) = decode(trailers(MAX_TRAILERS / 1024, "").as_bytes()).unwrap();
+        assert!(done);
+        assert!(decode(trailers(MAX_TRAILERS / 1024, "y:\r\n").as_bytes()).is_err());
+        assert!(decode(trailers(MAX_TRAILERS / 1024 - 1, &format!("y:{}\r\n", "a".repeat(1021))).as_bytes()).is_err());
+
+        // incomplete trailers at the limit does not underflow
+        let mut decoder = ChunkedDecoder::default();
+        let mut src = BytesMut::from(trailers(MAX_TRAILERS / 1024, "").trim_end());
+        assert!(!decoder.decode(&mut src, &mut BytesMut::new()).unwrap());
+        src.extend_from_slice(b"\r\n\r\n");
+        assert!(decoder.decode(&mut src, &mut BytesMut::new()).unwrap());
+    }
+}
@@ -3,15 +3,15 @@ use bytes::Bytes;
 use std::{
     convert::Infallible,
     future::{Ready, ready},
-    io,
     string::FromUtf8Error,
 };
 
-use super::{Collect, FromRequest, FromRequestParts, Parts, Request};
+use super::{BodyError, Collect, FromRequest, FromRequestParts, LengthLimitError, Parts, Request};
 use crate::{
-    futures::{FutureExt, Map, MapErr, MapOk, TryFutureExt},
+    futures::{FutureExt, Map, MapOk, TryFutureExt},
     helpers::BadRequest,
-    http::Method,
+    http::{Method, StatusCode},
+    response::{IntoResponse, Response},
 };
 
 // NOTE:
@@ -34,7 +34,7 @@ from_parts! {
     Method,
     Error = Infallible;
     Future = Ready<Result<Self,Infallible>>;
-    (parts) => ready(Ok(parts.method()))
+    (parts) => ready(Ok(parts.method().clone()))
 }
 
 macro_rules! from_request {
@@ -78,37 +78,50 @@ from_request! {
 
 from_request! {
     Bytes,
-    Error = BadRequest<io::Error>;
-    Future = MapErr<Collect, fn(io::Error) -> BadRequest<io::Error>>;
-    (req) => req.into_body().collect().map_err(BadRequest)
+    Error = BodyError;
+    Future = Collect;
+    (req) => req.into_body().collect()
 }
 
 from_request! {
     Vec<u8>,
-    Error = BadRequest<io::Error>;
+    Error = BodyError;
     Future = MapOk<<Bytes as FromRequest>::Future, fn(Bytes) -> Vec<u8>>;
     (req) => Bytes::from_request(req).map_ok(Into::into as _)
 }
 
 from_request! {
     String,
-    Error = BadRequest<BytesUtf8Error>;
+    Error = BytesUtf8Error;
     Future = Map<
         <Bytes as FromRequest>::Future,
-        fn(Result<Bytes, BadRequest<io::Error>>) -> Result<String, BadRequest<BytesUtf8Error>>,
+        fn(Result<Bytes, BodyError>) -> Result<String, BytesUtf8Error>,
     >;
     (req) => Bytes::from_request(req).map(map_to_string)
 }
 
-fn map_to_string(result: Result<Bytes, BadRequest<io::Error>>) -> Result<String, BadRequest<BytesUtf8Error>> {
-    String::from_utf8(result.map_err(BadRequest::map)?.into())
-        .map_err(Into::<BytesUtf8Error>::into)
-        .map_err(Into::into)
+fn map_to_string(result: Result<Bytes, BodyError>) -> Result<String, BytesUtf8Error> {
+    Ok(String::from_utf8(result?.into())?)
+}
+
+impl IntoResponse for BodyError {
+    fn into_response(self) -> Response {
+        match self {
+            BodyError::Io(_) => BadRequest(self).into_response(),
+            BodyError::LengthLimit(error) => error.into_response(),
+        }
+    }
+}
+
+impl IntoResponse for LengthLimitError {
+    fn into_response(self) -> Response {
+        (StatusCode::CONTENT_TOO_LARGE, self.to_string()).into_response()
+    }
 }
 
 #[derive(Debug)]
 pub enum BytesUtf8Error {
-    Io(io::Error),
+    Body(BodyError),
     FromUtf8(FromUtf8Error),
 }
 
@@ -118,15 +131,15 @@ impl std::fmt::Display for BytesUtf8Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         use std::fmt::Display;
         match self {
-            BytesUtf8Error::Io(error) => Display::fmt(error, f),
+            BytesUtf8Error::Body(error) => Display::fmt(error, f),
             BytesUtf8Error::FromUtf8(error) => Display::fmt(error, f),
         }
     }
 }
 
-impl From<io::Error> for BytesUtf8Error {
-    fn from(value: io::Error) -> Self {
-        Self::Io(value)
+impl From<BodyError> for BytesUtf8Error {
+    fn from(value: BodyError) -> Self {
+        Self::Body(value)
     }
 }
 
@@ -136,3 +149,12 @@ impl From<FromUtf8Error> for BytesUtf8Error {
     }
 }
 
+
+impl IntoResponse for BytesUtf8Error {
+    fn into_response(self) -> Response {
+        match self {
+            BytesUtf8Error::Body(error) => error.into_response(),
+            BytesUtf8Error::FromUtf8(_) => BadRequest(self).into_response(),
+        }
+    }
+}
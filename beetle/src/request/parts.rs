@@ -1,14 +1,14 @@
 use crate::{
     common::ByteStr,
     headers::HeaderMap,
-    http::{Extensions, Method, Version},
+    http::{Extensions, Method, Uri, Version},
 };
 
 /// HTTP Request Parts.
 #[derive(Default)]
 pub struct Parts {
     method: Method,
-    path: ByteStr,
+    uri: Uri,
     version: Version,
     headers: HeaderMap,
     extensions: Extensions,
@@ -17,14 +17,14 @@ pub struct Parts {
 impl Parts {
     pub(crate) fn new(
         method: Method,
-        path: ByteStr,
+        uri: Uri,
         version: Version,
         headers: HeaderMap,
         extensions: Extensions,
     ) -> Self {
         Self {
             method,
-            path,
+            uri,
             version,
             headers,
             extensions,
@@ -32,13 +32,28 @@ impl Parts {
     }
 
     /// Returns HTTP Method.
-    pub fn method(&self) -> Method {
-        self.method
+    pub fn method(&self) -> &Method {
+        &self.method
+    }
+
+    /// Returns HTTP Uri.
+    pub fn uri(&self) -> &Uri {
+        &self.uri
+    }
+
+    /// Returns mutable reference to HTTP Uri.
+    pub fn uri_mut(&mut self) -> &mut Uri {
+        &mut self.uri
     }
 
     /// Returns HTTP Path.
     pub fn path(&self) -> &ByteStr {
-        &self.path
+        self.uri.path()
+    }
+
+    /// Returns HTTP Query string, without the leading `?`.
+    pub fn query(&self) -> Option<&str> {
+        self.uri.query()
     }
 
     /// Returns HTTP Version.
@@ -51,6 +66,11 @@ impl Parts {
         &self.headers
     }
 
+    /// Returns mutable reference to HTTP Headers.
+    pub fn headers_mut(&mut self) -> &mut HeaderMap {
+        &mut self.headers
+    }
+
     pub fn extensions(&self) -> &Extensions {
         &self.extensions
     }
@@ -64,7 +84,7 @@ impl std::fmt::Debug for Parts {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         f.debug_struct("Parts")
             .field("method", &self.method)
-            .field("path", &self.path)
+            .field("uri", &self.uri)
             .field("version", &self.version)
             .field("headers", &self.headers())
             .finish()
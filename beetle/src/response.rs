@@ -59,6 +59,11 @@ impl Response {
         (self.parts,self.body)
     }
 
+    /// Returns reference to [`Body`].
+    pub fn body(&self) -> &Body {
+        &self.body
+    }
+
     /// Consume response into [`Body`].
     pub fn into_body(self) -> Body {
         self.body
@@ -77,10 +82,20 @@ impl Response {
         self.parts.status()
     }
 
+    /// Returns mutable reference to HTTP Status Code.
+    pub fn status_mut(&mut self) -> &mut StatusCode {
+        self.parts.status_mut()
+    }
+
     /// Returns HTTP HeaderMap.
     pub fn headers(&self) -> &HeaderMap {
         self.parts.headers()
     }
+
+    /// Returns mutable reference to HTTP HeaderMap.
+    pub fn headers_mut(&mut self) -> &mut HeaderMap {
+        self.parts.headers_mut()
+    }
 }
 
 impl std::fmt::Debug for Response {
@@ -1,9 +1,13 @@
 use bytes::Bytes;
+use futures_core::Stream;
 use std::{
     io,
+    pin::Pin,
     task::{Context, Poll},
 };
 
+type BoxStream = Pin<Box<dyn Stream<Item = io::Result<Bytes>> + Send>>;
+
 /// HTTP Response Body.
 pub struct Body {
     kind: Kind,
@@ -11,8 +15,18 @@ pub struct Body {
 
 enum Kind {
     Bytes(Bytes),
+    Stream {
+        stream: Option<SyncStream>,
+        len: Option<usize>,
+    },
 }
 
+/// Stream that is only accessed via exclusive reference.
+struct SyncStream(BoxStream);
+
+// SAFETY: the inner stream is never accessed via shared reference
+unsafe impl Sync for SyncStream { }
+
 impl Body {
     /// Create empty [`Body`].
     pub fn empty() -> Self {
@@ -28,17 +42,66 @@ impl Body {
         }
     }
 
-    /// Returns the body length.
-    pub fn content_len(&self) -> usize {
+    /// Create [`Body`] from a stream with unknown length.
+    ///
+    /// The response will be sent with `Transfer-Encoding: chunked`.
+    pub fn stream<S>(stream: S) -> Self
+    where
+        S: Stream<Item = io::Result<Bytes>> + Send + 'static,
+    {
+        Self {
+            kind: Kind::Stream {
+                stream: Some(SyncStream(Box::pin(stream))),
+                len: None,
+            },
+        }
+    }
+
+    /// Create [`Body`] from a stream with known length.
+    ///
+    /// The stream must yield exactly `len` bytes, otherwise the connection is closed after at
+    /// most `len` bytes is written.
+    pub fn sized_stream<S>(len: usize, stream: S) -> Self
+    where
+        S: Stream<Item = io::Result<Bytes>> + Send + 'static,
+    {
+        Self {
+            kind: Kind::Stream {
+                stream: Some(SyncStream(Box::pin(stream))),
+                len: Some(len),
+            },
+        }
+    }
+
+    /// Returns the body length, or `None` if the length is unknown.
+    pub fn content_len(&self) -> Option<usize> {
         match &self.kind {
-            Kind::Bytes(b) => b.len(),
+            Kind::Bytes(b) => Some(b.len()),
+            Kind::Stream { len, .. } => *len,
+        }
+    }
+
+    /// Returns the inner bytes if the body is not a stream, otherwise returns the body back.
+    pub fn try_into_bytes(self) -> Result<Bytes, Self> {
+        match self.kind {
+            Kind::Bytes(b) => Ok(b),
+            kind => Err(Self { kind }),
         }
     }
 
-    /// Poll for data.
-    pub(crate) fn poll_data(&mut self, _: &mut Context) -> Poll<io::Result<Bytes>> {
+    /// Poll for data, returns `None` when stream is exhausted.
+    pub(crate) fn poll_data(&mut self, cx: &mut Context) -> Poll<Option<io::Result<Bytes>>> {
         match &mut self.kind {
-            Kind::Bytes(b) => Poll::Ready(Ok(std::mem::take(b))),
+            Kind::Bytes(b) if b.is_empty() => Poll::Ready(None),
+            Kind::Bytes(b) => Poll::Ready(Some(Ok(std::mem::take(b)))),
+            Kind::Stream { stream: Some(stream), .. } => {
+                let data = std::task::ready!(stream.0.as_mut().poll_next(cx));
+                if data.is_none() {
+                    self.kind = Kind::Stream { stream: None, len: self.content_len() };
+                }
+                Poll::Ready(data)
+            }
+            Kind::Stream { stream: None, .. } => Poll::Ready(None),
         }
     }
 
@@ -46,6 +109,7 @@ impl Body {
     pub fn is_end_stream(&self) -> bool {
         match &self.kind {
             Kind::Bytes(b) => b.is_empty(),
+            Kind::Stream { stream, .. } => stream.is_none(),
         }
     }
 }
@@ -13,20 +13,6 @@ pub struct Parts {
 }
 
 impl Parts {
-    pub(crate) fn new(
-        version: Version,
-        status: StatusCode,
-        headers: HeaderMap,
-        extensions: Extensions,
-    ) -> Self {
-        Self {
-            version,
-            status,
-            headers,
-            extensions,
-        }
-    }
-
     /// Returns HTTP Version.
     pub fn version(&self) -> Version {
         self.version
@@ -51,6 +37,14 @@ impl Parts {
     pub fn headers_mut(&mut self) -> &mut HeaderMap {
         &mut self.headers
     }
+
+    pub fn extensions(&self) -> &Extensions {
+        &self.extensions
+    }
+
+    pub fn extensions_mut(&mut self) -> &mut Extensions {
+        &mut self.extensions
+    }
 }
 
 impl std::fmt::Debug for Parts {
@@ -1,21 +1,45 @@
 use bytes::{BufMut, BytesMut};
 
 use super::{Parts, Response};
-use crate::headers::HeaderValue;
+use crate::{headers::HeaderValue, http::StatusCode};
 
 /// perform a post write response
 ///
 /// - add httpdate
-/// - add content length
+/// - add content length, or chunked transfer encoding if body length is unknown
+/// - `204 No Content` have neither, `304 Not Modified` keep the content length set by user
 pub fn validate(res: &mut Response) {
     // todo!("add httpdate")
 
-    let mut b = itoa::Buffer::new();
-    let content_len = b.format(res.body.content_len());
-    res.parts.headers_mut().insert(
-        "content-length",
-        HeaderValue::try_copy_from_string(content_len).unwrap(),
-    );
+    match res.parts.status() {
+        StatusCode::NO_CONTENT => {
+            res.parts.headers_mut().remove("content-length");
+            res.parts.headers_mut().remove("transfer-encoding");
+            return;
+        }
+        StatusCode::NOT_MODIFIED => {
+            res.parts.headers_mut().remove("transfer-encoding");
+            return;
+        }
+        _ => {}
+    }
+
+    match res.body.content_len() {
+        Some(len) => {
+            let mut b = itoa::Buffer::new();
+            res.parts.headers_mut().insert(
+                "content-length",
+                HeaderValue::try_copy_from_string(b.format(len)).unwrap(),
+            );
+        }
+        None => {
+            res.parts.headers_mut().remove("content-length");
+            res.parts.headers_mut().insert(
+                "transfer-encoding",
+                HeaderValue::from_static("chunked"),
+            );
+        }
+    }
 }
 
 /// write http response parts into buffer
@@ -33,3 +57,33 @@ pub fn write(parts: &Parts, bytes: &mut BytesMut) {
     bytes.extend_from_slice(b"\r\n");
 }
 
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::response::{Body, IntoResponse};
+
+    fn header<'a>(res: &'a Response, name: &str) -> Option<&'a [u8]> {
+        res.headers().get(name).map(HeaderValue::as_bytes)
+    }
+
+    #[test]
+    fn validate_length() {
+        let mut res = Response::new(Body::bytes("hello"));
+        validate(&mut res);
+        assert_eq!(header(&res, "content-length"), Some(&b"5"[..]));
+        assert_eq!(header(&res, "transfer-encoding"), None);
+
+        let mut res = (StatusCode::NO_CONTENT, String::from("hello")).into_response();
+        validate(&mut res);
+        assert_eq!(header(&res, "content-length"), None);
+        assert_eq!(header(&res, "transfer-encoding"), None);
+
+        // length of the representation that would have been sent
+        let mut res = StatusCode::NOT_MODIFIED.into_response();
+        res.headers_mut().insert("content-length", HeaderValue::from_static("42"));
+        validate(&mut res);
+        assert_eq!(header(&res, "content-length"), Some(&b"42"[..]));
+        assert_eq!(header(&res, "transfer-encoding"), None);
+    }
+}
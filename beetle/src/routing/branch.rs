@@ -1,11 +1,12 @@
-use std::convert::Infallible;
+use std::{convert::Infallible, future::Ready};
 
 use super::Matcher;
 use crate::{
     futures::{EitherInto, FutureExt},
-    http::Method,
+    headers::HeaderValue,
+    http::{Method, StatusCode},
     request::Request,
-    response::Response,
+    response::{IntoResponse, Response},
     routing::handler::HandlerService,
     service::{HttpService, Service, http::MethodNotAllowed},
 };
@@ -15,11 +16,17 @@ use crate::{
 /// user typically does not interact with this directly,
 /// instead use [`route`] method, or [`get`] or [`post`] function
 ///
-/// [`route`]: Router::route
+/// branch built from method functions answer `OPTIONS` and `405 Method Not Allowed` with
+/// `Allow` header listing the registered methods, `HEAD` request is handled by `GET` service
+/// if there is no `HEAD` service
+///
+/// [`route`]: super::Router::route
 pub struct Branch<S,F> {
     matcher: Matcher,
     inner: S,
     fallback: F,
+    /// registered methods of the method branch chain, only the outermost branch holds it
+    allow: Option<Vec<Method>>,
 }
 
 macro_rules! fn_router {
@@ -30,16 +37,27 @@ macro_rules! fn_router {
                 matcher: Method::$method.into(),
                 inner: HandlerService::new(f),
                 fallback: MethodNotAllowed,
+                allow: Some(vec![Method::$method]),
             }
         }
     };
     (self $name:ident $method:ident $doc:literal) => {
         #[doc = $doc]
-        pub fn $name<S2,F2>(self, f: F2) -> Branch<HandlerService<F2, S2>, Branch<S, F>> {
+        pub fn $name<S2,F2>(mut self, f: F2) -> Branch<HandlerService<F2, S2>, Branch<S, F>> {
+            let mut matcher = Matcher::from(Method::$method);
+            let mut allow = self.allow.take();
+            if let Some(allow) = &mut allow {
+                // `HEAD` is already handled by inner branch
+                if allow.contains(&Method::HEAD) {
+                    matcher = matcher.without_head();
+                }
+                allow.push(Method::$method);
+            }
             Branch {
-                matcher: Method::$method.into(),
+                matcher,
                 inner: HandlerService::new(f),
                 fallback: self,
+                allow,
             }
         }
     };
@@ -50,10 +68,17 @@ fn_router!(post POST "setup POST service");
 fn_router!(put PUT "setup PUT service");
 fn_router!(patch PATCH "setup PATCH service");
 fn_router!(delete DELETE "setup DELETE service");
+fn_router!(head HEAD "setup HEAD service");
+fn_router!(options OPTIONS "setup OPTIONS service");
+
+/// setup service for any method
+pub fn any<F,S>(f: F) -> Branch<HandlerService<F,S>,MethodNotAllowed> {
+    Branch::new((), HandlerService::new(f), MethodNotAllowed)
+}
 
 impl<S, F> Branch<S, F> {
     pub fn new(matcher: impl Into<Matcher>, inner: S, fallback: F) -> Self {
-        Self { matcher: matcher.into(), inner, fallback }
+        Self { matcher: matcher.into(), inner, fallback, allow: None }
     }
 
     fn_router!(self get GET "add GET service");
@@ -61,8 +86,31 @@ impl<S, F> Branch<S, F> {
     fn_router!(self put PUT "add PUT service");
     fn_router!(self patch PATCH "add PATCH service");
     fn_router!(self delete DELETE "add DELETE service");
+    fn_router!(self head HEAD "add HEAD service");
+    fn_router!(self options OPTIONS "add OPTIONS service");
+}
+
+/// `OPTIONS` or `405 Method Not Allowed` response with `Allow` header
+pub(crate) fn not_allowed(allow: &[Method], method: &Method) -> Response {
+    let mut methods: Vec<&str> = Vec::with_capacity(allow.len() + 1);
+    let head = allow.contains(&Method::GET).then_some(&Method::HEAD);
+    for method in allow.iter().chain(head).chain(Some(&Method::OPTIONS)) {
+        if !methods.contains(&method.as_str()) {
+            methods.push(method.as_str());
+        }
+    }
+    let value = methods.join(", ");
+
+    let mut response = match method {
+        Method::OPTIONS => StatusCode::NO_CONTENT.into_response(),
+        _ => StatusCode::METHOD_NOT_ALLOWED.into_response(),
+    };
+    response.headers_mut().insert("allow", HeaderValue::from_string(value));
+    response
 }
 
+type Either<L, R> = EitherInto<L, R, Result<Response, Infallible>>;
+
 impl<S,F> Service<Request> for Branch<S,F>
 where
     S: HttpService,
@@ -70,13 +118,64 @@ where
 {
     type Response = Response;
     type Error = Infallible;
-    type Future = EitherInto<S::Future,F::Future,Result<Response,Infallible>>;
+    type Future = Either<Ready<Result<Response, Infallible>>, Either<S::Future, F::Future>>;
 
     fn call(&self, req: Request) -> Self::Future {
+        if let Some(allow) = &self.allow
+            && !allow.contains(req.method())
+            && !(matches!(req.method(), Method::HEAD) && allow.contains(&Method::GET))
+        {
+            return std::future::ready(Ok(not_allowed(allow, req.method()))).left_into();
+        }
+
         match self.matcher == req {
-            true => self.inner.call(req).left_into(),
-            false => self.fallback.call(req).right_into(),
+            true => self.inner.call(req).left_into().right_into(),
+            false => self.fallback.call(req).right_into().right_into(),
         }
     }
+
+    fn methods(&self) -> Option<&[Method]> {
+        self.allow.as_deref()
+    }
 }
 
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing::{body, call, request};
+
+    fn allow(response: &Response) -> Option<&str> {
+        response.headers().get("allow").and_then(|value| value.as_str().ok())
+    }
+
+    #[test]
+    fn method_not_allowed() {
+        let branch = get(|| async { String::from("get") }).post(|| async { String::from("post") });
+
+        let response = call(&branch, request(Method::POST, "/", &[]));
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(body(response), "post");
+
+        let response = call(&branch, request(Method::DELETE, "/", &[]));
+        assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(allow(&response), Some("GET, POST, HEAD, OPTIONS"));
+    }
+
+    #[test]
+    fn options() {
+        let branch = get(|| async { String::from("get") }).put(|| async { String::from("put") });
+
+        let response = call(&branch, request(Method::OPTIONS, "/", &[]));
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert_eq!(allow(&response), Some("GET, PUT, HEAD, OPTIONS"));
+
+        // explicit `OPTIONS` service take precedence
+        let branch = post(|| async { String::new() }).options(|| async { String::from("options") });
+        let response = call(&branch, request(Method::OPTIONS, "/", &[]));
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(body(response), "options");
+
+        let response = call(&branch, request(Method::GET, "/", &[]));
+        assert_eq!(allow(&response), Some("POST, OPTIONS"));
+    }
+}
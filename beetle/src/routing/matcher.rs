@@ -1,25 +1,62 @@
+use super::MatchedPath;
 use crate::{http::Method, request::Request};
 
 /// partially match request
+///
+/// path is compared against the pattern resolved by [`Router`][super::Router], see
+/// [`MatchedPath`], or exactly against the request path if the request is not routed
+///
+/// `GET` method also match `HEAD` request
 #[derive(Clone, Default)]
 pub struct Matcher {
     path: Option<&'static str>,
     method: Option<Method>,
+    head: bool,
+}
+
+impl Matcher {
+    fn new(path: Option<&'static str>, method: Option<Method>) -> Self {
+        let head = matches!(method, Some(Method::GET));
+        Self { path, method, head }
+    }
+
+    /// returns the path pattern
+    pub(crate) fn path(&self) -> Option<&'static str> {
+        self.path
+    }
+
+    /// returns the method
+    pub(crate) fn method(&self) -> Option<&Method> {
+        self.method.as_ref()
+    }
+
+    /// `GET` method does not match `HEAD` request, used when `HEAD` is explicitly handled
+    pub(crate) fn without_head(mut self) -> Self {
+        self.head = false;
+        self
+    }
+
+    /// returns `true` if request method match, path is not compared
+    pub(crate) fn matches_method(&self, method: &Method) -> bool {
+        match &self.method {
+            Some(expected) => expected == method || (self.head && matches!(method, Method::HEAD)),
+            None => true,
+        }
+    }
 }
 
 impl PartialEq<Request> for Matcher {
     fn eq(&self, other: &Request) -> bool {
         if let Some(path) = self.path {
-            if other.path().eq(path) {
+            let matched = match other.extensions().get::<MatchedPath>() {
+                Some(matched) => matched.as_str() == path,
+                None => other.path() == path,
+            };
+            if !matched {
                 return false;
             }
         }
-        if let Some(method) = &self.method {
-            if method != &other.method() {
-                return false;
-            }
-        }
-        true
+        self.matches_method(other.method())
     }
 }
 
@@ -34,6 +71,34 @@ macro_rules! matcher_from {
 }
 
 matcher_from!(_,() => ::default());
-matcher_from!(value,Method => { method: Some(value), ..Default::default() });
-matcher_from!(value,&'static str => { path: Some(value), ..Default::default() });
-matcher_from!((p,m),(&'static str,Method) => { path: Some(p), method: Some(m) });
+matcher_from!(value,Method => ::new(None, Some(value)));
+matcher_from!(value,&'static str => ::new(Some(value), None));
+matcher_from!((p,m),(&'static str,Method) => ::new(Some(p), Some(m)));
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Router, get, http::StatusCode, testing::{body, call, request}};
+
+    #[test]
+    fn head_fallback() {
+        let matcher = Matcher::from(Method::GET);
+        assert!(matcher.matches_method(&Method::HEAD));
+        assert!(!matcher.clone().without_head().matches_method(&Method::HEAD));
+        assert!(!Matcher::from(Method::POST).matches_method(&Method::HEAD));
+
+        let router = Router::new().route("/", get(|| async { String::from("get") }));
+        let response = call(&router, request(Method::HEAD, "/", &[]));
+        assert_eq!(response.status(), StatusCode::OK);
+        // body is skipped when the response is written
+        assert_eq!(body(response), "get");
+
+        // explicit `HEAD` service take precedence
+        let router = Router::new().route(
+            "/",
+            get(|| async { String::from("get") }).head(|| async { String::from("head") }),
+        );
+        let response = call(&router, request(Method::HEAD, "/", &[]));
+        assert_eq!(body(response), "head");
+    }
+}
@@ -0,0 +1,81 @@
+use std::convert::Infallible;
+
+use super::table::RouteIndex;
+use crate::{
+    futures::{EitherInto, FutureExt},
+    request::Request,
+    response::Response,
+    service::{HttpService, Service},
+};
+
+/// service that combine routes of two routers
+///
+/// user typically does not interact with this directly, instead use [`merge`] method
+///
+/// request is passed to `inner` if its path is handled by the merged router, otherwise it
+/// is passed to `fallback`
+///
+/// [`merge`]: super::Router::merge
+pub struct Merge<T, F> {
+    inner: T,
+    fallback: F,
+    /// route index of the merged router, sorted
+    indices: Vec<usize>,
+    /// nest prefix of the merged router
+    nests: Vec<&'static str>,
+    /// nest prefix of the router merged into
+    fallback_nests: Vec<&'static str>,
+    /// merged router has fallback or route without path
+    catch_all: bool,
+}
+
+impl<T, F> Merge<T, F> {
+    pub(crate) fn new(
+        inner: T,
+        fallback: F,
+        indices: Vec<usize>,
+        nests: Vec<&'static str>,
+        fallback_nests: Vec<&'static str>,
+        catch_all: bool,
+    ) -> Self {
+        Self { inner, fallback, indices, nests, fallback_nests, catch_all }
+    }
+
+    /// returns `true` if request is handled by the merged router
+    fn handles(&self, req: &Request) -> bool {
+        // route is already selected by the outer router
+        if let Some(index) = req.extensions().get::<RouteIndex>() {
+            return self.indices.binary_search(&index.0).is_ok();
+        }
+        let path = req.path().as_str();
+        if is_nested(&self.nests, path) {
+            return true;
+        }
+        self.catch_all && !is_nested(&self.fallback_nests, path)
+    }
+}
+
+/// returns `true` if path is under any of the prefix
+pub(crate) fn is_nested(prefixes: &[&str], path: &str) -> bool {
+    prefixes.iter().any(|prefix| match path.strip_prefix(prefix) {
+        Some(rest) => rest.is_empty() || rest.starts_with('/'),
+        None => false,
+    })
+}
+
+impl<T, F> Service<Request> for Merge<T, F>
+where
+    T: HttpService,
+    F: HttpService,
+{
+    type Response = Response;
+    type Error = Infallible;
+    type Future = EitherInto<T::Future, F::Future, Result<Response, Infallible>>;
+
+    fn call(&self, req: Request) -> Self::Future {
+        match self.handles(&req) {
+            true => self.inner.call(req).left_into(),
+            false => self.fallback.call(req).right_into(),
+        }
+    }
+}
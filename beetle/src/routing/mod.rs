@@ -4,11 +4,19 @@ mod router;
 mod branch;
 mod matcher;
 mod state;
+mod tree;
+mod params;
+mod nest;
+mod merge;
+mod table;
 
 pub mod handler;
 
-pub use router::Router;
-pub use branch::{Branch, get, post, put, patch, delete};
+pub use router::{Router, BoxRouter};
+pub use branch::{Branch, get, post, put, patch, delete, head, options, any};
 pub use matcher::Matcher;
 pub use state::State;
-
+pub use nest::Nest;
+pub use merge::Merge;
+pub use table::{Route, Fallback};
+pub use params::{Path, Params, MatchedPath, OriginalUri, FromParams, FromParam, PathError};
@@ -0,0 +1,128 @@
+use std::convert::Infallible;
+
+use super::OriginalUri;
+use crate::{
+    futures::{EitherInto, FutureExt},
+    request::Request,
+    response::Response,
+    service::{HttpService, Service},
+};
+
+/// service that mount another service under a path prefix
+///
+/// user typically does not interact with this directly,
+/// instead use [`nest`] or [`nest_service`] method
+///
+/// the prefix is removed from the request uri before passed to the nested service, the
+/// original uri is available as [`OriginalUri`]
+///
+/// [`nest`]: super::Router::nest
+/// [`nest_service`]: super::Router::nest_service
+pub struct Nest<T, F> {
+    prefix: &'static str,
+    inner: T,
+    fallback: F,
+    /// returns `true` if nested service handle the stripped path, otherwise the unmodified
+    /// request is passed to `fallback`
+    handles: fn(&T, &str) -> bool,
+}
+
+impl<T, F> Nest<T, F> {
+    pub(crate) fn new(prefix: &'static str, inner: T, fallback: F, handles: fn(&T, &str) -> bool) -> Self {
+        Self { prefix, inner, fallback, handles }
+    }
+}
+
+impl<T, F> Service<Request> for Nest<T, F>
+where
+    T: HttpService,
+    F: HttpService,
+{
+    type Response = Response;
+    type Error = Infallible;
+    type Future = EitherInto<T::Future, F::Future, Result<Response, Infallible>>;
+
+    fn call(&self, mut req: Request) -> Self::Future {
+        let Some(uri) = req.uri().strip_prefix(self.prefix) else {
+            return self.fallback.call(req).right_into();
+        };
+
+        if !(self.handles)(&self.inner, uri.path()) {
+            return self.fallback.call(req).right_into();
+        }
+
+        let original = std::mem::replace(req.uri_mut(), uri);
+        let extensions = req.extensions_mut();
+        if extensions.get::<OriginalUri>().is_none() {
+            extensions.insert(OriginalUri(original));
+        }
+
+        self.inner.call(req).left_into()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        Router, get,
+        http::{Method, StatusCode},
+        request::Request,
+        response::IntoResponse,
+        routing::OriginalUri,
+        service::servicefn::service_fn,
+        testing::{body, call, request},
+    };
+
+    async fn uris(OriginalUri(original): OriginalUri, req: Request) -> String {
+        format!("{} {}", original.as_str(), req.uri().as_str())
+    }
+
+    #[test]
+    fn strip_prefix() {
+        let api = Router::new().route("/", get(uris)).route("/users", get(uris));
+        let router = Router::new().nest("/api", api);
+
+        let response = call(&router, request(Method::GET, "/api/users?id=1", &[]));
+        assert_eq!(body(response), "/api/users?id=1 /users?id=1");
+
+        let response = call(&router, request(Method::GET, "/api", &[]));
+        assert_eq!(body(response), "/api /");
+
+        // prefix match whole segments
+        let response = call(&router, request(Method::GET, "/apiusers", &[]));
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn nested_original_uri() {
+        let v1 = Router::new().route("/users", get(uris));
+        let router = Router::new().nest("/api", Router::new().nest("/v1", v1));
+
+        // the outermost uri is kept
+        let response = call(&router, request(Method::GET, "/api/v1/users", &[]));
+        assert_eq!(body(response), "/api/v1/users /users");
+    }
+
+    #[test]
+    fn trailing_slash_prefix() {
+        let router = Router::new().nest("/api/", Router::new().route("/users", get(uris)));
+
+        let response = call(&router, request(Method::GET, "/api/users", &[]));
+        assert_eq!(body(response), "/api/users /users");
+    }
+
+    #[test]
+    fn nest_service() {
+        let service = service_fn(|req: Request| {
+            let response = req.uri().as_str().to_owned().into_response();
+            std::future::ready(Ok::<_, std::convert::Infallible>(response))
+        });
+        let router = Router::new().nest_service("/static/", service);
+
+        let response = call(&router, request(Method::POST, "/static/a/b.txt", &[]));
+        assert_eq!(body(response), "/a/b.txt");
+
+        let response = call(&router, request(Method::GET, "/other", &[]));
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,285 @@
+use std::future::{Ready, ready};
+
+use crate::{
+    common::ByteStr,
+    helpers::BadRequest,
+    http::{StatusCode, Uri},
+    request::{FromRequestParts, Parts},
+    response::{IntoResponse, Response},
+};
+
+/// Path pattern of the route matched by [`Router`][super::Router].
+///
+/// This is inserted into request extensions.
+#[derive(Clone, Debug)]
+pub struct MatchedPath(pub(crate) ByteStr);
+
+impl MatchedPath {
+    /// Returns the matched pattern, e.g: `"/users/:id"`.
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+impl FromRequestParts for MatchedPath {
+    type Error = StatusCode;
+
+    type Future = Ready<Result<Self, StatusCode>>;
+
+    fn from_request_parts(parts: &mut Parts) -> Self::Future {
+        ready(parts.extensions().get::<Self>().cloned().ok_or(StatusCode::INTERNAL_SERVER_ERROR))
+    }
+}
+
+/// Request [`Uri`] before the path prefix is removed by [`Router::nest`].
+///
+/// This is inserted into request extensions by the outermost nesting. When extracted outside
+/// nested router, the request uri is returned.
+///
+/// [`Router::nest`]: super::Router::nest
+#[derive(Clone, Debug)]
+pub struct OriginalUri(pub Uri);
+
+impl FromRequestParts for OriginalUri {
+    type Error = std::convert::Infallible;
+
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request_parts(parts: &mut Parts) -> Self::Future {
+        ready(Ok(match parts.extensions().get::<Self>() {
+            Some(uri) => uri.clone(),
+            None => OriginalUri(parts.uri().clone()),
+        }))
+    }
+}
+
+/// Path parameters captured by [`Router`][super::Router].
+///
+/// Values are percent decoded, except wildcard tail which is kept as is. This is inserted
+/// into request extensions.
+#[derive(Clone, Default, Debug)]
+pub struct Params {
+    params: Vec<(ByteStr, ByteStr)>,
+}
+
+impl Params {
+    /// Returns the value of parameter with given `name`.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.params.iter().find(|(k, _)| k == name).map(|(_, v)| v.as_str())
+    }
+
+    /// Returns an iterator over the `(name, value)` pairs.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.params.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    /// Returns the number of parameters.
+    pub fn len(&self) -> usize {
+        self.params.len()
+    }
+
+    /// Returns `true` if there is no parameters.
+    pub fn is_empty(&self) -> bool {
+        self.params.is_empty()
+    }
+
+    pub(crate) fn push(&mut self, name: ByteStr, value: ByteStr) {
+        self.params.push((name, value));
+    }
+}
+
+// ===== Extractor =====
+
+/// Extract path parameters.
+///
+/// `T` can be a single value when the route have exactly one parameter, a tuple of values in
+/// the order they appear in the pattern, or the raw [`Params`].
+#[derive(Debug)]
+pub struct Path<T>(pub T);
+
+impl<T: FromParams> FromRequestParts for Path<T> {
+    type Error = PathError;
+
+    type Future = Ready<Result<Self, PathError>>;
+
+    fn from_request_parts(parts: &mut Parts) -> Self::Future {
+        ready(match parts.extensions().get::<Params>() {
+            Some(params) => T::from_params(params).map(Path),
+            None => Err(PathError::Missing),
+        })
+    }
+}
+
+/// A type that can be constructed from all captured [`Params`].
+pub trait FromParams: Sized {
+    fn from_params(params: &Params) -> Result<Self, PathError>;
+}
+
+/// A type that can be constructed from single path parameter.
+pub trait FromParam: Sized {
+    fn from_param(value: &ByteStr) -> Option<Self>;
+}
+
+impl FromParams for Params {
+    fn from_params(params: &Params) -> Result<Self, PathError> {
+        Ok(params.clone())
+    }
+}
+
+impl<T: FromParam> FromParams for T {
+    fn from_params(params: &Params) -> Result<Self, PathError> {
+        match params.params.as_slice() {
+            [(name, value)] => T::from_param(value).ok_or_else(|| PathError::Invalid(name.clone())),
+            _ => Err(PathError::Count { expected: 1, found: params.len() }),
+        }
+    }
+}
+
+impl FromParam for ByteStr {
+    fn from_param(value: &ByteStr) -> Option<Self> {
+        Some(value.clone())
+    }
+}
+
+macro_rules! from_param_str {
+    ($($ty:ty),*) => {
+        $(
+            impl FromParam for $ty {
+                fn from_param(value: &ByteStr) -> Option<Self> {
+                    value.parse().ok()
+                }
+            }
+        )*
+    };
+}
+
+from_param_str!(String, bool, char, u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64);
+
+macro_rules! from_params_tuple {
+    ($len:literal, $($t:ident $v:ident),*) => {
+        impl<$($t: FromParam),*> FromParams for ($($t,)*) {
+            fn from_params(params: &Params) -> Result<Self, PathError> {
+                let [$($v),*] = params.params.as_slice() else {
+                    return Err(PathError::Count { expected: $len, found: params.len() });
+                };
+                Ok(($(
+                    $t::from_param(&$v.1).ok_or_else(|| PathError::Invalid($v.0.clone()))?,
+                )*))
+            }
+        }
+    };
+}
+
+from_params_tuple!(1, T1 t1);
+from_params_tuple!(2, T1 t1, T2 t2);
+from_params_tuple!(3, T1 t1, T2 t2, T3 t3);
+from_params_tuple!(4, T1 t1, T2 t2, T3 t3, T4 t4);
+
+// ===== Error =====
+
+/// Error returned from [`Path`] extractor.
+#[derive(Debug)]
+pub enum PathError {
+    /// Request is not routed by [`Router`][super::Router].
+    Missing,
+    /// Number of parameters does not match.
+    Count { expected: usize, found: usize },
+    /// Parameter with given name failed to parse.
+    Invalid(ByteStr),
+}
+
+impl std::error::Error for PathError { }
+
+impl std::fmt::Display for PathError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PathError::Missing => f.write_str("no path parameters found"),
+            PathError::Count { expected, found } => {
+                write!(f, "expected {expected} path parameters, found {found}")
+            }
+            PathError::Invalid(name) => write!(f, "invalid path parameter `{name}`"),
+        }
+    }
+}
+
+impl IntoResponse for PathError {
+    fn into_response(self) -> Response {
+        match self {
+            PathError::Invalid(_) => BadRequest(self).into_response(),
+            _ => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bytes::Bytes;
+
+    use crate::{Router, get, http::Method, service::HttpService, testing::{body, call, request}};
+
+    fn get_path<S: HttpService>(router: &Router<S>, uri: &'static str) -> (u16, Bytes) {
+        let response = call(router, request(Method::GET, uri, &[]));
+        (response.status().status(), body(response))
+    }
+
+    #[test]
+    fn single_param() {
+        async fn user(Path(id): Path<u32>) -> String { format!("user {id}") }
+        async fn name(Path(name): Path<String>) -> String { name }
+
+        let router = Router::new()
+            .route("/users/:id", get(user))
+            .route("/names/:name", get(name));
+        assert_eq!(get_path(&router, "/users/12"), (200, "user 12".into()));
+        assert_eq!(get_path(&router, "/names/a%20b"), (200, "a b".into()));
+    }
+
+    #[test]
+    fn tuple_params() {
+        async fn post(Path((user, post)): Path<(String, u64)>) -> String { format!("{user} {post}") }
+
+        let router = Router::new().route("/users/:user/posts/:post", get(post));
+        assert_eq!(get_path(&router, "/users/john/posts/7"), (200, "john 7".into()));
+    }
+
+    #[test]
+    fn struct_params() {
+        struct File {
+            dir: String,
+            rest: String,
+        }
+
+        impl FromParams for File {
+            fn from_params(params: &Params) -> Result<Self, PathError> {
+                let get = |name| match params.get(name) {
+                    Some(value) => Ok(value.to_owned()),
+                    None => Err(PathError::Invalid(ByteStr::from_static(name))),
+                };
+                Ok(File { dir: get("dir")?, rest: get("rest")? })
+            }
+        }
+
+        async fn file(Path(file): Path<File>) -> String { format!("{} {}", file.dir, file.rest) }
+
+        let router = Router::new().route("/files/:dir/*rest", get(file));
+        assert_eq!(get_path(&router, "/files/docs/a/b.txt"), (200, "docs a/b.txt".into()));
+    }
+
+    #[test]
+    fn rejection() {
+        async fn user(Path(id): Path<u32>) -> String { format!("user {id}") }
+        async fn pair(Path(pair): Path<(u32, u32)>) -> String { format!("{pair:?}") }
+
+        let router = Router::new()
+            .route("/users/:id", get(user))
+            .route("/pairs/:a", get(pair));
+
+        let (status, body) = get_path(&router, "/users/abc");
+        assert_eq!(status, 400);
+        assert!(body.starts_with(b"invalid path parameter `id`"));
+
+        // mismatched parameter count is a server error
+        assert_eq!(get_path(&router, "/pairs/1").0, 500);
+    }
+}
@@ -1,57 +1,404 @@
-use std::convert::Infallible;
+use std::{
+    borrow::Cow,
+    convert::Infallible,
+    sync::atomic::{AtomicUsize, Ordering},
+};
 
-use super::{Branch, Matcher, State};
+use super::{
+    Fallback, MatchedPath, Matcher, Merge, Nest, Params, Route, State,
+    merge::is_nested,
+    table::RouteTable,
+    tree::Tree,
+};
 use crate::{
+    common::ByteStr,
     helpers::Layer,
+    http::{Method, percent_decode},
     request::Request,
     response::Response,
-    service::{HttpService, Service, http::NotFound},
+    service::{BoxCloneService, HttpService, Service, http::NotFound},
 };
 
 /// route builder
 ///
-/// see [module level documentation](self) for more on routing
+/// path patterns are resolved once per request using a radix tree, the matched pattern and
+/// captured parameters are inserted into request extensions as [`MatchedPath`] and
+/// [`Params`], then the request is dispatched to the first route registered for the pattern
+/// and request method
+///
+/// if the path is matched but no route handle the request method, `405 Method Not Allowed`
+/// is returned
+///
+/// see [module level documentation](super) for more on routing
 pub struct Router<S> {
-    inner: S,
+    service: S,
+    /// select route by the resolved route id and request method
+    table: RouteTable,
+    /// map path pattern into `(pattern, route id)`
+    tree: Tree<(ByteStr, usize)>,
+    /// has fallback or route without path, which handle any path
+    catch_all: bool,
+    /// prefix of nested services
+    nests: Vec<&'static str>,
+    /// registered routes, in registration order
+    routes: Vec<RouteEntry>,
+}
+
+/// route id and route index are unique across routers, so merged router can be dispatched
+/// by the route selected by the router it merged into
+fn next_id() -> usize {
+    static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+    NEXT_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// registered route, used for listing and conflict detection
+#[derive(Clone)]
+struct RouteEntry {
+    /// [`None`] for any method
+    method: Option<Method>,
+    /// [`None`] for route without path
+    pattern: Option<ByteStr>,
+    /// route id resolved by this router path table, [`None`] for route without path or route
+    /// of nested router
+    id: Option<usize>,
+    /// route handle every method of the path, e.g: method branch which respond with
+    /// `405 Method Not Allowed`
+    exclusive: bool,
+}
+
+impl RouteEntry {
+    fn conflicts(&self, other: &RouteEntry) -> bool {
+        self.id.is_some()
+            && self.id == other.id
+            && (self.exclusive || other.exclusive || self.method == other.method)
+    }
+
+    fn nested(mut self, prefix: &str) -> RouteEntry {
+        let pattern = match self.pattern.as_deref() {
+            Some("/") => prefix.to_owned(),
+            Some(pattern) => format!("{prefix}{pattern}"),
+            None => format!("{prefix}/*"),
+        };
+        self.pattern = Some(pattern.into());
+        self.id = None;
+        self
+    }
+}
+
+impl std::fmt::Display for RouteEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.method {
+            Some(method) => f.write_str(method.as_str())?,
+            None => f.write_str("ANY")?,
+        }
+        match &self.pattern {
+            Some(pattern) => write!(f, " {pattern}"),
+            None => f.write_str(" *"),
+        }
+    }
 }
 
-impl Router<NotFound> {
+impl Router<Fallback<NotFound>> {
     /// create new `Router`
-    pub fn new() -> Router<NotFound> {
-        Router { inner: NotFound }
+    pub fn new() -> Router<Fallback<NotFound>> {
+        Router {
+            service: Fallback::new(NotFound),
+            table: RouteTable::default(),
+            tree: Tree::new(),
+            catch_all: false,
+            nests: Vec::new(),
+            routes: Vec::new(),
+        }
     }
 }
 
-impl<S> Router<S> {
+/// [`Router`] with type erased service, returned from [`Router::boxed`]
+pub type BoxRouter = Router<BoxCloneService>;
+
+impl<S> Router<Fallback<S>> {
     /// create new `Router` with custom fallback instead of 404 NotFound
-    pub fn with_fallback(fallback: S) -> Router<S> {
-        Router { inner: fallback }
+    pub fn with_fallback(fallback: S) -> Router<Fallback<S>> {
+        Router {
+            service: Fallback::new(fallback),
+            table: RouteTable::default(),
+            tree: Tree::new(),
+            catch_all: true,
+            nests: Vec::new(),
+            routes: Vec::new(),
+        }
     }
+}
 
+impl<S> Router<S> {
     /// layer current router service
     ///
-    /// this is low level way to interact with `Router`
+    /// this is low level way to interact with `Router`, only routes registered before the
+    /// layer is wrapped by it
     ///
     /// see [`Layer`] for more information
     pub fn layer<L>(self, layer: L) -> Router<L::Service>
     where
         L: Layer<S>,
     {
-        Router {
-            inner: layer.layer(self.inner),
-        }
+        self.wrap(|service| layer.layer(service))
     }
 
     /// assign new route
-    pub fn route<R>(self, matcher: impl Into<Matcher>, route: R) -> Router<Branch<R, S>> {
-        Router {
-            inner: Branch::new(matcher, route, self.inner),
+    ///
+    /// path may contains named segment, `/users/:id`, or wildcard tail, `/files/*rest`,
+    /// which can be extracted using [`Path`][super::Path]
+    ///
+    /// named segment is percent decoded, while wildcard tail is not, so encoded `/` cannot
+    /// introduce new path segment, e.g: `%2F..%2F` stays as is
+    ///
+    /// # Panics
+    ///
+    /// panics if the path pattern is invalid or conflicting with other route
+    pub fn route<R>(mut self, matcher: impl Into<Matcher>, route: R) -> Router<Route<R, S>>
+    where
+        R: HttpService,
+    {
+        let matcher = matcher.into();
+        let pattern = matcher.path().map(ByteStr::from_static);
+        // route with the same pattern share the route id
+        let existing = self.routes.iter().find(|e| e.id.is_some() && e.pattern == pattern);
+        let existing = existing.and_then(|e| e.id);
+        let id = pattern.as_ref().map(|_| existing.unwrap_or_else(next_id));
+        let entries = match (matcher.method(), route.methods()) {
+            (Some(method), _) => vec![(Some(method.clone()), false)],
+            (None, Some(methods)) => methods.iter().map(|m| (Some(m.clone()), true)).collect(),
+            (None, None) => vec![(None, true)],
+        };
+        let entries = entries.into_iter().map(|(method, exclusive)| RouteEntry {
+            method,
+            pattern: pattern.clone(),
+            id,
+            exclusive,
+        });
+        self.register(entries.collect());
+
+        match (pattern, id) {
+            (Some(pattern), Some(id)) if existing.is_none() => self.insert(pattern, id),
+            (Some(_), _) => {}
+            (None, _) => self.catch_all = true,
+        }
+        let index = next_id();
+        self.table.insert(id, index, matcher);
+        self.wrap(|service| Route::new(index, route, service))
+    }
+
+    /// combine routes of another router
+    ///
+    /// request which path is handled by `router` is passed to it, otherwise it is passed
+    /// to this router, this allows routers built separately to be combined
+    ///
+    /// # Panics
+    ///
+    /// panics if both router have the same path pattern, nest prefix or fallback
+    pub fn merge<S2>(mut self, router: Router<S2>) -> Router<Merge<S2, S>> {
+        if self.catch_all && router.catch_all {
+            panic!("cannot merge router which both have fallback or route without path");
+        }
+        for entry in &router.routes {
+            if let Some(pattern) = &entry.pattern
+                && entry.id.is_some()
+                && self.routes.iter().any(|e| e.id.is_some() && e.pattern.as_ref() == Some(pattern))
+            {
+                panic!("conflicting route `{entry}`, path is already registered");
+            }
+        }
+        for prefix in &router.nests {
+            if self.nests.contains(prefix) {
+                panic!("conflicting nest `{prefix}`, prefix is already registered");
+            }
+        }
+
+        let Router { service, table, tree: _, catch_all, nests, routes } = router;
+        let mut ids = Vec::new();
+        for entry in &routes {
+            if let (Some(pattern), Some(id)) = (&entry.pattern, entry.id)
+                && !ids.contains(&id)
+            {
+                self.insert(pattern.clone(), id);
+                ids.push(id);
+            }
         }
+        let indices = table.indices();
+        self.table.extend(table);
+        let fallback_nests = self.nests.clone();
+        self.nests.extend_from_slice(&nests);
+        self.routes.extend(routes);
+        self.catch_all |= catch_all;
+        self.wrap(|fallback| Merge::new(service, fallback, indices, nests, fallback_nests, catch_all))
+    }
+
+    /// mount another router under path prefix
+    ///
+    /// the prefix is removed from the request path before passed to the nested router, the
+    /// original uri can be extracted using [`OriginalUri`][super::OriginalUri]
+    ///
+    /// request that does not match any route of the nested router is passed to this router
+    /// fallback, unless the nested router has its own fallback
+    ///
+    /// # Panics
+    ///
+    /// panics if the prefix is not a static path other than `/`
+    pub fn nest<S2>(
+        mut self,
+        prefix: &'static str,
+        router: Router<S2>,
+    ) -> Router<Nest<Router<S2>, S>> {
+        let prefix = self.nest_prefix(prefix);
+        let entries = router.routes.iter().map(|entry| entry.clone().nested(prefix));
+        self.routes.extend(entries);
+        self.wrap(|service| Nest::new(prefix, router, service, Router::handles))
+    }
+
+    /// mount a service under path prefix
+    ///
+    /// all request under the prefix is passed to the service, with the prefix removed from
+    /// the request path
+    ///
+    /// # Panics
+    ///
+    /// panics if the prefix is not a static path other than `/`
+    pub fn nest_service<T>(mut self, prefix: &'static str, service: T) -> Router<Nest<T, S>> {
+        let prefix = self.nest_prefix(prefix);
+        self.routes.push(RouteEntry {
+            method: None,
+            pattern: Some(format!("{prefix}/*").into()),
+            id: None,
+            exclusive: true,
+        });
+        self.wrap(|fallback| Nest::new(prefix, service, fallback, |_, _| true))
+    }
+
+    /// returns an iterator over registered `(method, path pattern)`, in registration order
+    ///
+    /// method is [`None`] if the route handle any method, and pattern is `*` if the route
+    /// does not have path, routes of nested router are prefixed with the nest prefix
+    ///
+    /// ```
+    /// # use beetle::{Router, service::HttpService};
+    /// fn log_routes<S: HttpService>(router: &Router<S>) {
+    ///     for (method, path) in router.routes() {
+    ///         println!("{} {path}", method.map(|m| m.as_str()).unwrap_or("ANY"));
+    ///     }
+    /// }
+    /// ```
+    pub fn routes(&self) -> impl Iterator<Item = (Option<&Method>, &str)> {
+        self.routes.iter().map(|entry| {
+            (entry.method.as_ref(), entry.pattern.as_deref().unwrap_or("*"))
+        })
+    }
+
+    /// returns `true` if the path is handled by any route
+    fn handles(&self, path: &str) -> bool {
+        self.catch_all || self.tree.at(path).is_some() || is_nested(&self.nests, path)
+    }
+
+    /// erase the router service type
+    ///
+    /// each layer, state, nest and merge add to the router type, erasing it allows the router
+    /// to be stored in struct field or returned from function, and reduce compile time of
+    /// large router
+    ///
+    /// ```
+    /// # use beetle::{get, routing::BoxRouter, Router};
+    /// async fn list() -> String { String::from("users") }
+    ///
+    /// fn users() -> BoxRouter {
+    ///     Router::new()
+    ///         .route("/users", get(list))
+    ///         .boxed()
+    /// }
+    /// ```
+    pub fn boxed(self) -> BoxRouter
+    where
+        S: HttpService,
+    {
+        self.wrap(BoxCloneService::new)
     }
 
     pub fn state<T>(self, state: T) -> Router<State<T, S>> {
+        self.wrap(|service| State::new(state, service))
+    }
+
+    fn wrap<T>(self, f: impl FnOnce(S) -> T) -> Router<T> {
         Router {
-            inner: State::new(state, self.inner),
+            service: f(self.service),
+            table: self.table,
+            tree: self.tree,
+            catch_all: self.catch_all,
+            nests: self.nests,
+            routes: self.routes,
+        }
+    }
+
+    /// check for conflicting route before add it to the route list
+    fn register(&mut self, entries: Vec<RouteEntry>) {
+        for entry in &entries {
+            if let Some(existing) = self.routes.iter().find(|e| e.conflicts(entry)) {
+                panic!("conflicting route `{entry}` with `{existing}`, chain the methods in \
+                    single route instead, e.g: `get(a).post(b)`");
+            }
+        }
+        self.routes.extend(entries);
+    }
+
+    fn insert(&mut self, pattern: ByteStr, id: usize) {
+        if let Err(err) = self.tree.insert(pattern.clone(), (pattern, id)) {
+            panic!("{err}");
+        }
+    }
+
+    /// validate and normalize nest prefix, trailing slash is removed
+    fn nest_prefix(&mut self, prefix: &'static str) -> &'static str {
+        assert!(prefix.starts_with('/'), "nest prefix must starts with `/`: {prefix:?}");
+        assert!(
+            !prefix.contains([':', '*']),
+            "nest prefix cannot contains path parameter: {prefix:?}"
+        );
+        let prefix = prefix.trim_end_matches('/');
+        assert!(!prefix.is_empty(), "cannot nest at root path");
+        assert!(!self.nests.contains(&prefix), "conflicting nest `{prefix}`, prefix is already registered");
+        self.nests.push(prefix);
+        prefix
+    }
+
+    /// resolve request path against the path table, returns the matched route id
+    fn resolve(&self, req: &mut Request) -> Option<usize> {
+        let path = req.path().clone();
+        match self.tree.at(&path) {
+            Some(((pattern, id), captures)) => {
+                let extensions = req.extensions_mut();
+                extensions.insert(MatchedPath(pattern.clone()));
+                if captures.is_empty() {
+                    return Some(*id);
+                }
+                if extensions.get::<Params>().is_none() {
+                    extensions.insert(Params::default());
+                }
+                // wildcard tail is kept raw, decoding it may turn `%2F..%2F` into `/../`
+                let wildcard = pattern.rsplit('/').next().is_some_and(|last| last.starts_with('*'));
+                let last = captures.len() - 1;
+
+                // nested router append its own parameters
+                let params = extensions.get_mut::<Params>().unwrap();
+                for (i, (name, value)) in captures.into_iter().enumerate() {
+                    let value = match percent_decode(value) {
+                        _ if wildcard && i == last => path.slice_ref(value),
+                        Cow::Borrowed(value) => path.slice_ref(value),
+                        Cow::Owned(value) => value.into(),
+                    };
+                    params.push(name.clone(), value);
+                }
+                Some(*id)
+            }
+            None => {
+                req.extensions_mut().remove::<MatchedPath>();
+                None
+            }
         }
     }
 }
@@ -64,14 +411,185 @@ where
     type Error = Infallible;
     type Future = S::Future;
 
-    fn call(&self, req: Request) -> Self::Future {
-        self.inner.call(req)
+    fn call(&self, mut req: Request) -> Self::Future {
+        let id = self.resolve(&mut req);
+        self.table.select(id, &mut req);
+        self.service.call(req)
     }
 }
 
-impl Default for Router<NotFound> {
+impl Default for Router<Fallback<NotFound>> {
     fn default() -> Self {
         Self::new()
     }
 }
 
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{get, post, routing::{any, handler::HandlerService}};
+
+    async fn handler() { }
+
+    fn routes<S: HttpService>(router: &Router<S>) -> Vec<String> {
+        router.routes().map(|(method, path)| match method {
+            Some(method) => format!("{} {path}", method.as_str()),
+            None => format!("ANY {path}"),
+        }).collect()
+    }
+
+    #[test]
+    fn list_routes() {
+        let api = Router::new()
+            .route("/", get(handler))
+            .route("/users/:id", any(handler));
+        let router = Router::new()
+            .route("/users", get(handler).post(handler))
+            .route(("/login", Method::POST), handler_service())
+            .nest("/api", api)
+            .nest_service("/static", NotFound);
+
+        assert_eq!(routes(&router), [
+            "GET /users",
+            "POST /users",
+            "POST /login",
+            "GET /api",
+            "ANY /api/users/:id",
+            "ANY /static/*",
+        ]);
+    }
+
+    #[test]
+    fn merge_routes() {
+        let users = Router::new().route("/users", get(handler));
+        let posts = Router::new().route("/posts", post(handler));
+        let router = users.merge(posts);
+
+        assert_eq!(routes(&router), ["GET /users", "POST /posts"]);
+        assert!(router.handles("/users"));
+        assert!(router.handles("/posts"));
+    }
+
+    #[test]
+    fn layered_routes() {
+        use crate::{helpers::BodyLimit, service::servicefn::service_fn};
+
+        // any service can be routed, layer forward the methods of the inner service
+        let service = service_fn(|_| std::future::ready(Ok::<_, Infallible>(Response::default())));
+        let router = Router::new()
+            .route("/users", BodyLimit::new(1024).layer(get(handler).post(handler)))
+            .route("/posts", BoxCloneService::new(service));
+        assert_eq!(routes(&router), ["GET /users", "POST /users", "ANY /posts"]);
+    }
+
+    #[test]
+    fn method_routes() {
+        // different method with method matcher does not conflict
+        let router = Router::new()
+            .route(("/users", Method::GET), handler_service())
+            .route(("/users", Method::POST), handler_service());
+        assert_eq!(routes(&router), ["GET /users", "POST /users"]);
+    }
+
+    #[test]
+    #[should_panic = "conflicting route `POST /users` with `GET /users`"]
+    fn conflict_path() {
+        routes(&Router::new()
+            .route("/users", get(handler))
+            .route("/users", post(handler)));
+    }
+
+    #[test]
+    #[should_panic = "conflicting route `GET /users`"]
+    fn conflict_method() {
+        routes(&Router::new()
+            .route(("/users", Method::GET), handler_service())
+            .route(("/users", Method::GET), handler_service()));
+    }
+
+    #[test]
+    #[should_panic = "conflicting route `GET /users`, path is already registered"]
+    fn conflict_merge() {
+        routes(&Router::new()
+            .route("/users", post(handler))
+            .merge(Router::new().route("/users", get(handler))));
+    }
+
+    #[test]
+    #[should_panic = "conflicting nest `/api`"]
+    fn conflict_nest() {
+        routes(&Router::new()
+            .nest_service("/api", NotFound)
+            .nest_service("/api/", NotFound));
+    }
+
+    #[test]
+    fn resolve_params() {
+        use crate::testing::request;
+
+        let router = Router::new()
+            .route("/users/:id", get(handler))
+            .route("/files/*rest", get(handler));
+
+        let resolve = |uri| {
+            let mut req = request(Method::GET, uri, &[]);
+            router.resolve(&mut req);
+            let params = req.extensions().get::<Params>().unwrap();
+            params.iter().map(|(k, v)| (k.to_owned(), v.to_owned())).collect::<Vec<_>>()
+        };
+        assert_eq!(resolve("/users/a%20b"), [("id".to_owned(), "a b".to_owned())]);
+        assert_eq!(resolve("/files/a%2F..%2Fb"), [("rest".to_owned(), "a%2F..%2Fb".to_owned())]);
+    }
+
+    #[test]
+    fn dispatch() {
+        use crate::testing::{body, call, request};
+
+        async fn users() -> String { String::from("users") }
+        async fn user() -> String { String::from("user") }
+        async fn create() -> String { String::from("create") }
+        async fn login() -> String { String::from("login") }
+        async fn posts() -> String { String::from("posts") }
+        async fn fallback() -> String { String::from("fallback") }
+
+        let router = Router::new()
+            .route(("/users", Method::GET), HandlerService::new(users))
+            .route("/users/:id", get(user))
+            .state(())
+            .route(("/users", Method::POST), HandlerService::new(create))
+            .merge(Router::new().route("/posts", get(posts)))
+            .route(("/login", Method::POST), HandlerService::new(login))
+            .route(("/login", Method::PUT), HandlerService::new(login))
+            .route(Method::PUT, HandlerService::new(fallback));
+
+        let get = |method, uri| {
+            let response = call(&router, request(method, uri, &[]));
+            (response.status().status(), body(response))
+        };
+        assert_eq!(get(Method::GET, "/users"), (200, "users".into()));
+        assert_eq!(get(Method::GET, "/users/12"), (200, "user".into()));
+        assert_eq!(get(Method::POST, "/users"), (200, "create".into()));
+        assert_eq!(get(Method::GET, "/posts"), (200, "posts".into()));
+        assert_eq!(get(Method::POST, "/login"), (200, "login".into()));
+        // route with path take precedence over route without path registered later
+        assert_eq!(get(Method::PUT, "/login"), (200, "login".into()));
+        // route without path handle method not handled by the path routes
+        assert_eq!(get(Method::PUT, "/users"), (200, "fallback".into()));
+        assert_eq!(get(Method::PUT, "/none"), (200, "fallback".into()));
+        assert_eq!(get(Method::DELETE, "/users/12").0, 405);
+        assert_eq!(get(Method::GET, "/none").0, 404);
+
+        // path registered with method matcher only
+        let response = call(&router, request(Method::DELETE, "/users", &[]));
+        assert_eq!(response.status().status(), 405);
+        assert_eq!(response.headers().get("allow").unwrap().as_bytes(), b"GET, POST, HEAD, OPTIONS");
+        let response = call(&router, request(Method::GET, "/login", &[]));
+        assert_eq!(response.status().status(), 405);
+        assert_eq!(response.headers().get("allow").unwrap().as_bytes(), b"POST, PUT, OPTIONS");
+    }
+
+    fn handler_service() -> crate::routing::handler::HandlerService<fn() -> std::future::Ready<()>, ()> {
+        crate::routing::handler::HandlerService::new(|| std::future::ready(()))
+    }
+}
@@ -1,4 +1,5 @@
 use crate::{
+    http::Method,
     request::Request,
     service::{HttpService, Service},
 };
@@ -27,5 +28,8 @@ where
         req.extensions_mut().insert(self.state.clone());
         self.inner.call(req)
     }
-}
 
+    fn methods(&self) -> Option<&[Method]> {
+        self.inner.methods()
+    }
+}
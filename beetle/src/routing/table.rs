@@ -0,0 +1,152 @@
+use std::{collections::HashMap, convert::Infallible, future::Ready};
+
+use super::{Matcher, branch::not_allowed};
+use crate::{
+    futures::{EitherInto, FutureExt},
+    http::Method,
+    request::Request,
+    response::Response,
+    service::{HttpService, Service},
+};
+
+/// route selected by [`Router`] for the request, inserted into request extensions
+///
+/// [`Router`]: super::Router
+#[derive(Clone, Copy)]
+pub(crate) struct RouteIndex(pub(crate) usize);
+
+/// path is matched but no route handle the request method, inserted into request extensions
+#[derive(Clone)]
+pub(crate) struct MethodNotAllowed(Vec<Method>);
+
+/// lookup of route index by the resolved route id and request method
+#[derive(Default)]
+pub(crate) struct RouteTable {
+    /// route index and matcher of each route id, in registration order
+    routes: HashMap<usize, Vec<(usize, Matcher)>>,
+    /// route index and matcher of route without path, in registration order
+    any: Vec<(usize, Matcher)>,
+}
+
+impl RouteTable {
+    /// `id` is [`None`] for route without path
+    pub(crate) fn insert(&mut self, id: Option<usize>, index: usize, matcher: Matcher) {
+        match id {
+            Some(id) => self.routes.entry(id).or_default().push((index, matcher)),
+            None => self.any.push((index, matcher)),
+        }
+    }
+
+    /// combine routes of merged router
+    pub(crate) fn extend(&mut self, other: RouteTable) {
+        for (id, routes) in other.routes {
+            self.routes.entry(id).or_default().extend(routes);
+        }
+        self.any.extend(other.any);
+    }
+
+    /// returns all route index, used by merged router
+    pub(crate) fn indices(&self) -> Vec<usize> {
+        let routes = self.routes.values().flatten().chain(&self.any);
+        let mut indices: Vec<usize> = routes.map(|(index, _)| *index).collect();
+        indices.sort_unstable();
+        indices
+    }
+
+    /// insert the route for the request into extensions
+    ///
+    /// route of the matched path take precedence over route without path, otherwise the
+    /// first registered route is selected
+    pub(crate) fn select(&self, id: Option<usize>, req: &mut Request) {
+        let routes = id.and_then(|id| self.routes.get(&id)).map(Vec::as_slice).unwrap_or_default();
+        let method = req.method();
+        let find = |routes: &[(usize, Matcher)]| {
+            routes.iter().find(|(_, matcher)| matcher.matches_method(method)).map(|(index, _)| *index)
+        };
+        let index = find(routes).or_else(|| find(&self.any));
+
+        let extensions = req.extensions_mut();
+        extensions.remove::<RouteIndex>();
+        extensions.remove::<MethodNotAllowed>();
+
+        if let Some(index) = index {
+            extensions.insert(RouteIndex(index));
+        } else if !routes.is_empty() {
+            // route without method always match, so all routes here have method
+            let allow = routes.iter().filter_map(|(_, matcher)| matcher.method().cloned());
+            extensions.insert(MethodNotAllowed(allow.collect()));
+        }
+    }
+}
+
+/// service registered to [`Router`]
+///
+/// user typically does not interact with this directly, instead use [`route`] method
+///
+/// request is passed to `inner` if the router select this route, otherwise it is passed
+/// to `fallback`
+///
+/// [`Router`]: super::Router
+/// [`route`]: super::Router::route
+pub struct Route<R, F> {
+    index: usize,
+    inner: R,
+    fallback: F,
+}
+
+impl<R, F> Route<R, F> {
+    pub(crate) fn new(index: usize, inner: R, fallback: F) -> Self {
+        Self { index, inner, fallback }
+    }
+}
+
+impl<R, F> Service<Request> for Route<R, F>
+where
+    R: HttpService,
+    F: HttpService,
+{
+    type Response = Response;
+    type Error = Infallible;
+    type Future = EitherInto<R::Future, F::Future, Result<Response, Infallible>>;
+
+    fn call(&self, req: Request) -> Self::Future {
+        match req.extensions().get::<RouteIndex>() {
+            Some(index) if index.0 == self.index => self.inner.call(req).left_into(),
+            _ => self.fallback.call(req).right_into(),
+        }
+    }
+}
+
+/// the innermost service of [`Router`]
+///
+/// user typically does not interact with this directly
+///
+/// respond with `405 Method Not Allowed` if the path is matched but no route handle the
+/// request method, otherwise the request is passed to `inner`
+///
+/// [`Router`]: super::Router
+pub struct Fallback<S> {
+    inner: S,
+}
+
+impl<S> Fallback<S> {
+    pub(crate) fn new(inner: S) -> Self {
+        Self { inner }
+    }
+}
+
+impl<S> Service<Request> for Fallback<S>
+where
+    S: HttpService,
+{
+    type Response = Response;
+    type Error = Infallible;
+    type Future = EitherInto<Ready<Result<Response, Infallible>>, S::Future, Result<Response, Infallible>>;
+
+    fn call(&self, req: Request) -> Self::Future {
+        match req.extensions().get::<MethodNotAllowed>() {
+            Some(allow) => std::future::ready(Ok(not_allowed(&allow.0, req.method()))).left_into(),
+            None => self.inner.call(req).right_into(),
+        }
+    }
+}
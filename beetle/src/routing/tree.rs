@@ -0,0 +1,313 @@
+//! radix tree route table
+use crate::common::ByteStr;
+
+/// radix tree that map path pattern into value
+///
+/// pattern is a static path that may contains:
+///
+/// - named segment, `/users/:id`, which match exactly one non empty segment
+/// - wildcard tail, `/files/*rest`, which match the rest of the path, it must be the last segment
+///
+/// when multiple pattern match the same path, static segment is prioritized over named
+/// segment, and named segment is prioritized over wildcard tail
+#[derive(Debug)]
+pub struct Tree<T> {
+    root: Node<T>,
+}
+
+#[derive(Debug)]
+struct Node<T> {
+    /// static bytes consumed by this node
+    prefix: ByteStr,
+    value: Option<T>,
+    /// first byte of each `statics` prefix
+    indices: Vec<u8>,
+    statics: Vec<Node<T>>,
+    param: Option<Box<(ByteStr, Node<T>)>>,
+    wildcard: Option<(ByteStr, T)>,
+}
+
+/// captured path parameter in `(name, value)` pair
+pub type Captures<'n, 'p> = Vec<(&'n ByteStr, &'p str)>;
+
+impl<T> Tree<T> {
+    /// create new empty tree
+    pub fn new() -> Self {
+        Self { root: Node::new(ByteStr::new()) }
+    }
+
+    /// insert new pattern
+    ///
+    /// # Errors
+    ///
+    /// returns error if pattern is invalid, or conflicting with other pattern
+    pub fn insert(&mut self, pattern: ByteStr, value: T) -> Result<(), InsertError> {
+        validate(&pattern)?;
+        self.root.insert(&pattern, pattern.as_str(), value)
+    }
+
+    /// find value that match given path
+    pub fn at<'n, 'p>(&'n self, path: &'p str) -> Option<(&'n T, Captures<'n, 'p>)> {
+        let mut captures = Vec::new();
+        let value = self.root.at(path, &mut captures)?;
+        Some((value, captures))
+    }
+}
+
+impl<T> Default for Tree<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Node<T> {
+    fn new(prefix: ByteStr) -> Self {
+        Self {
+            prefix,
+            value: None,
+            indices: Vec::new(),
+            statics: Vec::new(),
+            param: None,
+            wildcard: None,
+        }
+    }
+
+    /// `rest` is the remaining pattern after this node prefix
+    fn insert(&mut self, pattern: &ByteStr, rest: &str, value: T) -> Result<(), InsertError> {
+        if rest.is_empty() {
+            if self.value.is_some() {
+                return Err(InsertError::Duplicate(pattern.clone()));
+            }
+            self.value = Some(value);
+            return Ok(());
+        }
+
+        if let Some(name) = rest.strip_prefix('*') {
+            if self.wildcard.is_some() {
+                return Err(InsertError::Duplicate(pattern.clone()));
+            }
+            self.wildcard = Some((pattern.slice_ref(name), value));
+            return Ok(());
+        }
+
+        if let Some(param) = rest.strip_prefix(':') {
+            let end = param.find('/').unwrap_or(param.len());
+            let name = pattern.slice_ref(&param[..end]);
+
+            let node = match &mut self.param {
+                Some(node) if node.0 != name => {
+                    return Err(InsertError::Conflict {
+                        pattern: pattern.clone(),
+                        name: node.0.clone(),
+                    });
+                }
+                Some(node) => &mut node.1,
+                None => &mut self.param.insert(Box::new((name, Node::new(ByteStr::new())))).1,
+            };
+
+            return node.insert(pattern, &param[end..], value);
+        }
+
+        let end = rest.find([':', '*']).unwrap_or(rest.len());
+        let segment = &rest[..end];
+
+        let Some(i) = self.indices.iter().position(|&b| b == segment.as_bytes()[0]) else {
+            self.indices.push(segment.as_bytes()[0]);
+            self.statics.push(Node::new(pattern.slice_ref(segment)));
+            let node = self.statics.last_mut().unwrap();
+            return node.insert(pattern, &rest[end..], value);
+        };
+
+        let node = &mut self.statics[i];
+        let common = common_prefix(&node.prefix, segment);
+
+        if common < node.prefix.len() {
+            node.split(common);
+        }
+
+        node.insert(pattern, &rest[common..], value)
+    }
+
+    /// split current node at `at`, moving the rest into a new child
+    fn split(&mut self, at: usize) {
+        let prefix = self.prefix.clone();
+        let child = Node {
+            prefix: prefix.slice_ref(&prefix[at..]),
+            value: self.value.take(),
+            indices: std::mem::take(&mut self.indices),
+            statics: std::mem::take(&mut self.statics),
+            param: self.param.take(),
+            wildcard: self.wildcard.take(),
+        };
+        self.prefix = prefix.slice_ref(&prefix[..at]);
+        self.indices.push(child.prefix.as_bytes()[0]);
+        self.statics.push(child);
+    }
+
+    /// `path` is the remaining path after this node prefix
+    fn at<'n, 'p>(&'n self, path: &'p str, captures: &mut Captures<'n, 'p>) -> Option<&'n T> {
+        if path.is_empty()
+            && let Some(value) = &self.value
+        {
+            return Some(value);
+        }
+
+        if let Some(&b) = path.as_bytes().first()
+            && let Some(i) = self.indices.iter().position(|&i| i == b)
+        {
+            let node = &self.statics[i];
+            if let Some(rest) = path.strip_prefix(node.prefix.as_str())
+                && let Some(value) = node.at(rest, captures)
+            {
+                return Some(value);
+            }
+        }
+
+        if let Some(param) = &self.param {
+            let end = path.find('/').unwrap_or(path.len());
+            if end != 0 {
+                captures.push((&param.0, &path[..end]));
+                if let Some(value) = param.1.at(&path[end..], captures) {
+                    return Some(value);
+                }
+                captures.pop();
+            }
+        }
+
+        if let Some((name, value)) = &self.wildcard
+            && !path.is_empty()
+        {
+            captures.push((name, path));
+            return Some(value);
+        }
+
+        None
+    }
+}
+
+fn common_prefix(a: &str, b: &str) -> usize {
+    a.bytes().zip(b.bytes()).take_while(|(a, b)| a == b).count()
+}
+
+fn validate(pattern: &ByteStr) -> Result<(), InsertError> {
+    let invalid = |reason| Err(InsertError::Invalid { pattern: pattern.clone(), reason });
+
+    if !pattern.starts_with('/') {
+        return invalid("pattern must starts with `/`");
+    }
+
+    let mut segments = pattern[1..].split('/').peekable();
+
+    while let Some(segment) = segments.next() {
+        if let Some(name) = segment.strip_prefix(':').or_else(|| segment.strip_prefix('*')) {
+            if name.is_empty() {
+                return invalid("parameter name cannot be empty");
+            }
+            if name.contains([':', '*']) {
+                return invalid("only one parameter is allowed per segment");
+            }
+            if segment.starts_with('*') && segments.peek().is_some() {
+                return invalid("wildcard must be the last segment");
+            }
+        } else if segment.contains([':', '*']) {
+            return invalid("parameter must span the whole segment");
+        }
+    }
+
+    Ok(())
+}
+
+/// error when inserting pattern into [`Tree`]
+#[derive(Debug)]
+pub enum InsertError {
+    /// pattern is malformed
+    Invalid { pattern: ByteStr, reason: &'static str },
+    /// the exact same pattern already exists
+    Duplicate(ByteStr),
+    /// named segment in the same position have different name
+    Conflict { pattern: ByteStr, name: ByteStr },
+}
+
+impl std::error::Error for InsertError { }
+
+impl std::fmt::Display for InsertError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InsertError::Invalid { pattern, reason } => {
+                write!(f, "invalid route `{pattern}`: {reason}")
+            }
+            InsertError::Duplicate(pattern) => write!(f, "route `{pattern}` is already registered"),
+            InsertError::Conflict { pattern, name } => {
+                write!(f, "route `{pattern}` conflict with existing parameter `:{name}`")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn tree(patterns: &[&'static str]) -> Tree<&'static str> {
+        let mut tree = Tree::new();
+        for &pattern in patterns {
+            tree.insert(ByteStr::from_static(pattern), pattern).unwrap();
+        }
+        tree
+    }
+
+    fn at<'a>(tree: &'a Tree<&'static str>, path: &'a str) -> Option<(&'static str, Vec<(&'a str, &'a str)>)> {
+        let (value, captures) = tree.at(path)?;
+        Some((*value, captures.into_iter().map(|(k, v)| (k.as_str(), v)).collect()))
+    }
+
+    #[test]
+    fn static_routes() {
+        let tree = tree(&["/", "/users", "/user", "/users/all", "/about"]);
+
+        assert_eq!(at(&tree, "/"), Some(("/", vec![])));
+        assert_eq!(at(&tree, "/users"), Some(("/users", vec![])));
+        assert_eq!(at(&tree, "/user"), Some(("/user", vec![])));
+        assert_eq!(at(&tree, "/users/all"), Some(("/users/all", vec![])));
+        assert_eq!(at(&tree, "/about"), Some(("/about", vec![])));
+        assert_eq!(at(&tree, "/use"), None);
+        assert_eq!(at(&tree, "/users/"), None);
+    }
+
+    #[test]
+    fn params_and_priority() {
+        let tree = tree(&[
+            "/users/:id",
+            "/users/me",
+            "/users/:id/posts/:post",
+            "/files/*rest",
+            "/files/index",
+        ]);
+
+        assert_eq!(at(&tree, "/users/me"), Some(("/users/me", vec![])));
+        assert_eq!(at(&tree, "/users/12"), Some(("/users/:id", vec![("id", "12")])));
+        assert_eq!(
+            at(&tree, "/users/12/posts/3"),
+            Some(("/users/:id/posts/:post", vec![("id", "12"), ("post", "3")])),
+        );
+        assert_eq!(at(&tree, "/users/me/posts/3").unwrap().0, "/users/:id/posts/:post");
+        assert_eq!(at(&tree, "/users/"), None);
+        assert_eq!(at(&tree, "/files/index"), Some(("/files/index", vec![])));
+        assert_eq!(at(&tree, "/files/a/b.txt"), Some(("/files/*rest", vec![("rest", "a/b.txt")])));
+        assert_eq!(at(&tree, "/files/"), None);
+    }
+
+    #[test]
+    fn insert_errors() {
+        let mut tree = tree(&["/users/:id"]);
+
+        let mut insert = |p: &'static str| tree.insert(ByteStr::from_static(p), p);
+
+        assert!(matches!(insert("/users/:id"), Err(InsertError::Duplicate(_))));
+        assert!(matches!(insert("/users/:name"), Err(InsertError::Conflict { .. })));
+        assert!(matches!(insert("users"), Err(InsertError::Invalid { .. })));
+        assert!(matches!(insert("/a/*rest/b"), Err(InsertError::Invalid { .. })));
+        assert!(matches!(insert("/a/b:c"), Err(InsertError::Invalid { .. })));
+        assert!(matches!(insert("/a/:"), Err(InsertError::Invalid { .. })));
+    }
+}
@@ -4,22 +4,34 @@ use std::{
     pin::Pin,
     sync::Arc,
     task::{Context, Poll, ready},
+    time::Duration,
 };
 
 use crate::{
     Service,
     io::Listener,
     net::Socket,
-    service::{HttpService, tcp::TcpService},
+    service::{HttpService, tcp::{Config, TcpService}},
 };
 
+mod shutdown;
+
+pub(crate) use shutdown::{Shutdown, Watcher};
+
 #[cfg(feature = "tokio")]
 pub use rt_tokio::{Tokio, TokioServe, listen};
 
 pub fn serve<R: Runtime, S: HttpService>(listener: R::Listener, service: S) -> Serve<R, S> {
     Serve {
-        listener,
+        listener: Some(listener),
         service: Arc::new(service),
+        config: Config::new(),
+        shutdown: Shutdown::default(),
+        signal: None,
+        deadline: None,
+        on_accept_error: None,
+        backoff: None,
+        backoff_delay: Duration::ZERO,
     }
 }
 
@@ -28,21 +40,129 @@ pub fn serve<R: Runtime, S: HttpService>(listener: R::Listener, service: S) -> S
 pub trait Runtime {
     type Listener: Listener;
 
+    type Sleep: Future<Output = ()> + Send + 'static;
+
     fn spawn<F>(future: F)
     where
         F: Future + Send + 'static,
         F::Output: Send + 'static;
+
+    fn sleep(duration: Duration) -> Self::Sleep;
 }
 
 // ===== Futures =====
 
-#[derive(Debug)]
-pub struct Serve<R, S>
+type Signal = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+type ErrorCallback = Box<dyn Fn(&io::Error) + Send + Sync>;
+
+/// minimum delay before retrying accept after resource error
+const MIN_BACKOFF: Duration = Duration::from_millis(5);
+
+/// maximum delay before retrying accept after resource error
+const MAX_BACKOFF: Duration = Duration::from_secs(1);
+
+pin_project_lite::pin_project! {
+    pub struct Serve<R, S>
+    where
+        R: Runtime,
+    {
+        // `None` when shutting down
+        listener: Option<R::Listener>,
+        service: Arc<S>,
+        config: Config,
+        shutdown: Shutdown,
+        signal: Option<Signal>,
+        #[pin]
+        deadline: Option<R::Sleep>,
+        on_accept_error: Option<ErrorCallback>,
+        #[pin]
+        backoff: Option<R::Sleep>,
+        backoff_delay: Duration,
+    }
+}
+
+impl<R, S> Serve<R, S>
+where
+    R: Runtime,
+{
+    /// Set the http connection [`Config`].
+    pub fn with_config(mut self, config: Config) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Gracefully shutdown the server when `signal` completes.
+    ///
+    /// The server stop accepting new connection, and each connection finish its current
+    /// response and then closed. The server resolves when all connections are closed, or when
+    /// the [`shutdown_timeout`][Config::shutdown_timeout] passes.
+    pub fn with_graceful_shutdown<F>(mut self, signal: F) -> Self
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        self.signal = Some(Box::pin(signal));
+        self
+    }
+
+    /// Call `f` on every error when accepting connection.
+    ///
+    /// Accept error is either:
+    ///
+    /// - connection error, e.g: the connection is reset before accepted, accept is retried
+    ///   immediately
+    /// - fatal error, e.g: the listener is not listening, the server returns the error
+    /// - other error, e.g: too many open files, accept is retried with exponential backoff
+    pub fn on_accept_error<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&io::Error) + Send + Sync + 'static,
+    {
+        self.on_accept_error = Some(Box::new(f));
+        self
+    }
+}
+
+/// error that only affect the accepted connection
+fn is_connection_error(err: &io::Error) -> bool {
+    matches!(
+        err.kind(),
+        io::ErrorKind::ConnectionRefused
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::Interrupted
+            | io::ErrorKind::WouldBlock
+    )
+}
+
+/// error that the listener cannot recover from
+fn is_fatal_error(err: &io::Error) -> bool {
+    matches!(
+        err.kind(),
+        io::ErrorKind::InvalidInput | io::ErrorKind::NotConnected | io::ErrorKind::Unsupported
+    )
+}
+
+/// delay before the next accept retry, doubled on each consecutive error
+fn next_backoff(delay: Duration) -> Duration {
+    (delay * 2).clamp(MIN_BACKOFF, MAX_BACKOFF)
+}
+
+impl<R, S> std::fmt::Debug for Serve<R, S>
 where
     R: Runtime,
+    R::Listener: std::fmt::Debug,
+    S: std::fmt::Debug,
 {
-    listener: R::Listener,
-    service: Arc<S>,
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Serve")
+            .field("listener", &self.listener)
+            .field("service", &self.service)
+            .field("config", &self.config)
+            .field("shutdown", &self.shutdown)
+            .field("graceful_shutdown", &self.signal.is_some())
+            .field("backoff_delay", &self.backoff_delay)
+            .finish_non_exhaustive()
+    }
 }
 
 impl<R, S> Future for Serve<R, S>
@@ -54,12 +174,69 @@ where
     type Output = io::Result<()>;
 
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut me = self.project();
+
+        if let Some(signal) = me.signal
+            && signal.as_mut().poll(cx).is_ready()
+        {
+            #[cfg(feature = "log")]
+            log::debug!("graceful shutdown");
+            *me.signal = None;
+            *me.listener = None;
+            me.shutdown.trigger();
+            me.deadline.set(me.config.shutdown_timeout.map(R::sleep));
+        }
+
+        let Some(listener) = me.listener else {
+            if me.shutdown.poll_finished(cx).is_ready() {
+                return Poll::Ready(Ok(()));
+            }
+            if let Some(deadline) = me.deadline.as_pin_mut() {
+                ready!(deadline.poll(cx));
+                #[cfg(feature = "log")]
+                log::debug!("graceful shutdown timeout, remaining connections are abandoned");
+                return Poll::Ready(Ok(()));
+            }
+            return Poll::Pending;
+        };
+
         loop {
-            match ready!(self.listener.poll_accept(cx)) {
+            if let Some(backoff) = me.backoff.as_mut().as_pin_mut() {
+                ready!(backoff.poll(cx));
+                me.backoff.set(None);
+            }
+
+            match ready!(listener.poll_accept(cx)) {
                 Ok((io, _)) => {
-                    R::spawn(TcpService::new(self.service.clone()).call(io.into()));
+                    *me.backoff_delay = Duration::ZERO;
+                    let service = TcpService::new::<R>(me.service.clone(), me.config.clone())
+                        .with_shutdown(me.shutdown.clone());
+                    R::spawn(service.call(io.into()));
+                }
+                Err(err) => {
+                    if let Some(f) = me.on_accept_error {
+                        f(&err);
+                    }
+
+                    if is_connection_error(&err) {
+                        #[cfg(feature = "log")]
+                        log::debug!("accept error: {err}");
+                        continue;
+                    }
+
+                    if is_fatal_error(&err) {
+                        #[cfg(feature = "log")]
+                        log::error!("accept error: {err}");
+                        return Poll::Ready(Err(err));
+                    }
+
+                    *me.backoff_delay = next_backoff(*me.backoff_delay);
+
+                    #[cfg(feature = "log")]
+                    log::error!("accept error: {err}, retrying in {:?}", me.backoff_delay);
+
+                    me.backoff.set(Some(R::sleep(*me.backoff_delay)));
                 }
-                Err(_err) => {}
             }
         }
     }
@@ -70,7 +247,10 @@ where
 mod rt_tokio {
     use std::hint;
 
-    use tokio::net::{TcpListener, ToSocketAddrs};
+    use tokio::{
+        net::{TcpListener, ToSocketAddrs},
+        time::Sleep,
+    };
 
     use super::*;
 
@@ -80,6 +260,8 @@ mod rt_tokio {
     impl Runtime for Tokio {
         type Listener = TcpListener;
 
+        type Sleep = Pin<Box<Sleep>>;
+
         fn spawn<F>(future: F)
         where
             F: Future + Send + 'static,
@@ -87,6 +269,10 @@ mod rt_tokio {
         {
             tokio::spawn(future);
         }
+
+        fn sleep(duration: Duration) -> Self::Sleep {
+            Box::pin(tokio::time::sleep(duration))
+        }
     }
 
     /// Start the server using [`TcpListener`][tokio::net::TcpListener].
@@ -97,16 +283,52 @@ mod rt_tokio {
         service: S,
     ) -> TokioServe<S> {
         TokioServe {
+            config: Config::new(),
+            signal: None,
+            on_accept_error: None,
             phase: Phase::F1 { f: Box::pin(TcpListener::bind(addr)), s: service },
         }
     }
 
     pin_project_lite::pin_project! {
         pub struct TokioServe<S> {
+            config: Config,
+            signal: Option<Signal>,
+            on_accept_error: Option<ErrorCallback>,
             #[pin] phase: Phase<S>,
         }
     }
 
+    impl<S> TokioServe<S> {
+        /// Set the http connection [`Config`].
+        pub fn with_config(mut self, config: Config) -> Self {
+            self.config = config;
+            self
+        }
+
+        /// Gracefully shutdown the server when `signal` completes.
+        ///
+        /// See [`Serve::with_graceful_shutdown`] for more details.
+        pub fn with_graceful_shutdown<F>(mut self, signal: F) -> Self
+        where
+            F: Future<Output = ()> + Send + 'static,
+        {
+            self.signal = Some(Box::pin(signal));
+            self
+        }
+
+        /// Call `f` on every error when accepting connection.
+        ///
+        /// See [`Serve::on_accept_error`] for more details.
+        pub fn on_accept_error<F>(mut self, f: F) -> Self
+        where
+            F: Fn(&io::Error) + Send + Sync + 'static,
+        {
+            self.on_accept_error = Some(Box::new(f));
+            self
+        }
+    }
+
     pin_project_lite::pin_project! {
         #[project = Project]
         #[project_replace = Replace]
@@ -132,7 +354,10 @@ mod rt_tokio {
                     let Replace::F1 { f: _, s } = me.phase.as_mut().project_replace(Phase::Deez) else {
                         unsafe { hint::unreachable_unchecked() }
                     };
-                    me.phase.set(Phase::F2 { s: super::serve(io, s) });
+                    let mut serve = super::serve(io, s).with_config(me.config.clone());
+                    serve.signal = me.signal.take();
+                    serve.on_accept_error = me.on_accept_error.take();
+                    me.phase.set(Phase::F2 { s: serve });
                     self.poll(cx)
                 },
                 Project::F2 { s } => s.poll(cx),
@@ -142,3 +367,87 @@ mod rt_tokio {
     }
 }
 
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn accept_error() {
+        let error = |kind| io::Error::from(kind);
+
+        // connection error is retried immediately
+        for kind in [io::ErrorKind::ConnectionAborted, io::ErrorKind::ConnectionReset, io::ErrorKind::Interrupted] {
+            assert!(is_connection_error(&error(kind)));
+            assert!(!is_fatal_error(&error(kind)));
+        }
+
+        // resource error, e.g: EMFILE and ENOMEM, is retried with backoff
+        for err in [io::Error::from_raw_os_error(24), io::Error::from_raw_os_error(12)] {
+            assert!(!is_connection_error(&err));
+            assert!(!is_fatal_error(&err));
+        }
+
+        for kind in [io::ErrorKind::InvalidInput, io::ErrorKind::NotConnected, io::ErrorKind::Unsupported] {
+            assert!(!is_connection_error(&error(kind)));
+            assert!(is_fatal_error(&error(kind)));
+        }
+    }
+
+    #[test]
+    fn backoff() {
+        let mut delay = Duration::ZERO;
+        let mut delays = Vec::new();
+        for _ in 0..12 {
+            delay = next_backoff(delay);
+            delays.push(delay.as_millis());
+        }
+        assert_eq!(delays, [5, 10, 20, 40, 80, 160, 320, 640, 1000, 1000, 1000, 1000]);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn graceful_shutdown() {
+        use crate::{response::Response, service::servicefn::service_fn};
+        use std::convert::Infallible;
+        use tokio::{
+            io::{AsyncReadExt, AsyncWriteExt},
+            net::{TcpListener, TcpStream},
+            time::{sleep, timeout},
+        };
+
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+        runtime.block_on(async {
+            // response is still in flight when shutdown is triggered
+            let service = service_fn(|_| async {
+                sleep(Duration::from_millis(200)).await;
+                Ok::<_, Infallible>(Response::default())
+            });
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            let server = serve::<Tokio, _>(listener, service)
+                .with_graceful_shutdown(sleep(Duration::from_millis(100)));
+            let server = tokio::spawn(server);
+
+            let mut client = TcpStream::connect(addr).await.unwrap();
+            client.write_all(b"GET / HTTP/1.1\r\n\r\n").await.unwrap();
+            sleep(Duration::from_millis(150)).await;
+
+            // new connection is not accepted
+            assert!(TcpStream::connect(addr).await.is_err());
+
+            // in flight response is completed, then the connection is closed
+            let mut output = Vec::new();
+            timeout(Duration::from_secs(5), client.read_to_end(&mut output))
+                .await
+                .expect("connection is not closed")
+                .unwrap();
+            let output = String::from_utf8(output).unwrap();
+            assert!(output.starts_with("HTTP/1.1 200 OK\r\n"));
+            assert!(output.contains("connection: close\r\n"));
+
+            let result = timeout(Duration::from_secs(5), server).await.expect("server is not finished");
+            assert!(result.unwrap().is_ok());
+        });
+    }
+}
@@ -0,0 +1,99 @@
+//! graceful shutdown signal shared between server and its connections
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc, Mutex, MutexGuard, PoisonError,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    task::{Context, Poll, Waker},
+};
+
+/// server side handle of graceful shutdown
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Shutdown {
+    inner: Arc<Inner>,
+}
+
+/// connection side handle of graceful shutdown
+///
+/// the connection is considered finished when this is dropped
+#[derive(Debug)]
+pub(crate) struct Watcher {
+    id: usize,
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    closing: AtomicBool,
+    next_id: AtomicUsize,
+    wakers: Mutex<Wakers>,
+}
+
+#[derive(Debug, Default)]
+struct Wakers {
+    /// wakers of connections, keyed by watcher id
+    conns: HashMap<usize, Option<Waker>>,
+    /// waker of the server waiting for connections to finish
+    server: Option<Waker>,
+}
+
+impl Inner {
+    fn wakers(&self) -> MutexGuard<'_, Wakers> {
+        self.wakers.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Shutdown {
+    /// create new watcher for a connection
+    pub fn watch(&self) -> Watcher {
+        let id = self.inner.next_id.fetch_add(1, Ordering::Relaxed);
+        self.inner.wakers().conns.insert(id, None);
+        Watcher { id, inner: self.inner.clone() }
+    }
+
+    /// notify all connections to finish
+    pub fn trigger(&self) {
+        self.inner.closing.store(true, Ordering::Release);
+        let mut wakers = self.inner.wakers();
+        for waker in wakers.conns.values_mut().filter_map(Option::take) {
+            waker.wake();
+        }
+    }
+
+    /// poll until all connections are finished
+    pub fn poll_finished(&self, cx: &mut Context) -> Poll<()> {
+        let mut wakers = self.inner.wakers();
+        if wakers.conns.is_empty() {
+            return Poll::Ready(());
+        }
+        wakers.server = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl Watcher {
+    /// returns `true` if server is shutting down
+    pub fn is_closing(&self) -> bool {
+        self.inner.closing.load(Ordering::Acquire)
+    }
+
+    /// register waker to be woken when shutdown is triggered
+    pub fn register(&self, cx: &mut Context) {
+        if let Some(waker) = self.inner.wakers().conns.get_mut(&self.id) {
+            *waker = Some(cx.waker().clone());
+        }
+    }
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        let mut wakers = self.inner.wakers();
+        wakers.conns.remove(&self.id);
+        if wakers.conns.is_empty()
+            && let Some(waker) = wakers.server.take()
+        {
+            waker.wake();
+        }
+    }
+}
@@ -1,11 +1,16 @@
 //! asynchronous service
 use std::convert::Infallible;
 
-use crate::{request::Request, response::Response};
+use crate::{http::Method, request::Request, response::Response};
 
 pub mod servicefn;
+pub mod boxed;
 pub mod http;
 pub mod tcp;
+#[cfg(feature = "tokio")]
+pub mod fs;
+
+pub use boxed::BoxCloneService;
 
 pub trait Service<Request> {
     type Response;
@@ -15,6 +20,14 @@ pub trait Service<Request> {
     type Future: Future<Output = Result<Self::Response, Self::Error>>;
 
     fn call(&self, request: Request) -> Self::Future;
+
+    /// returns methods handled by this service, or [`None`] if it handle any method
+    ///
+    /// used by [`Router`][crate::Router] to list the registered routes and detect conflicting
+    /// registration, middleware should forward it to the inner service
+    fn methods(&self) -> Option<&[Method]> {
+        None
+    }
 }
 
 // ===== Http Service =====
@@ -50,6 +63,10 @@ where
     fn call(&self, request: Req) -> Self::Future {
         S::call(self, request)
     }
+
+    fn methods(&self) -> Option<&[Method]> {
+        S::methods(self)
+    }
 }
 
 impl<S, Req> Service<Req> for std::sync::Arc<S>
@@ -63,5 +80,9 @@ where
     fn call(&self, request: Req) -> Self::Future {
         S::call(self, request)
     }
+
+    fn methods(&self) -> Option<&[Method]> {
+        S::methods(self)
+    }
 }
 
@@ -0,0 +1,136 @@
+//! type erased service
+use std::{convert::Infallible, pin::Pin, sync::Arc};
+
+use super::{HttpService, Service};
+use crate::{http::Method, request::Request, response::Response};
+
+/// type erased [`HttpService`] future
+pub type BoxFuture = Pin<Box<dyn Future<Output = Result<Response, Infallible>> + Send + Sync>>;
+
+/// cloneable type erased [`HttpService`]
+///
+/// the inner service is shared, and its future is boxed, so the service type can be spelled
+/// out, e.g: stored in struct field or returned from function
+///
+/// cloning is cheap, it only increase reference count
+#[derive(Clone)]
+pub struct BoxCloneService {
+    inner: Arc<dyn Erased>,
+}
+
+impl BoxCloneService {
+    /// create new [`BoxCloneService`]
+    pub fn new<S: HttpService>(service: S) -> Self {
+        Self { inner: Arc::new(service) }
+    }
+}
+
+trait Erased: Send + Sync + 'static {
+    fn call(&self, req: Request) -> BoxFuture;
+
+    fn methods(&self) -> Option<&[Method]>;
+}
+
+impl<S: HttpService> Erased for S {
+    fn call(&self, req: Request) -> BoxFuture {
+        Box::pin(Service::call(self, req))
+    }
+
+    fn methods(&self) -> Option<&[Method]> {
+        Service::methods(self)
+    }
+}
+
+impl Service<Request> for BoxCloneService {
+    type Response = Response;
+    type Error = Infallible;
+    type Future = BoxFuture;
+
+    fn call(&self, req: Request) -> Self::Future {
+        self.inner.call(req)
+    }
+
+    fn methods(&self) -> Option<&[Method]> {
+        self.inner.methods()
+    }
+}
+
+impl std::fmt::Debug for BoxCloneService {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BoxCloneService").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        Router, get, post,
+        http::StatusCode,
+        testing::{body, call, request},
+    };
+
+    async fn list() -> String { String::from("list") }
+    async fn create() -> String { String::from("create") }
+
+    fn routes<S: HttpService>(router: &Router<S>) -> Vec<String> {
+        router.routes().map(|(method, path)| {
+            format!("{} {path}", method.map(Method::as_str).unwrap_or("ANY"))
+        }).collect()
+    }
+
+    #[test]
+    fn boxed_service() {
+        let service = BoxCloneService::new(get(list).post(create));
+        assert_eq!(Service::methods(&service), Some(&[Method::GET, Method::POST][..]));
+
+        // clone share the same service
+        let cloned = service.clone();
+        assert_eq!(body(call(&service, request(Method::GET, "/", &[]))), "list");
+        assert_eq!(body(call(&cloned, request(Method::POST, "/", &[]))), "create");
+        let response = call(&cloned, request(Method::DELETE, "/", &[]));
+        assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+    }
+
+    #[test]
+    fn boxed_route() {
+        let router = Router::new().route("/users", BoxCloneService::new(get(list).post(create)));
+        assert_eq!(routes(&router), ["GET /users", "POST /users"]);
+    }
+
+    #[test]
+    #[should_panic = "conflicting route `POST /users` with `POST /users`"]
+    fn boxed_route_conflict() {
+        let _ = Router::new()
+            .route("/users", BoxCloneService::new(post(create).get(list)))
+            .route(("/users", Method::POST), BoxCloneService::new(post(create)));
+    }
+
+    #[test]
+    fn boxed_router() {
+        let router = Router::new()
+            .route("/users", get(list).post(create))
+            .boxed()
+            .route("/posts", get(list));
+        assert_eq!(routes(&router), ["GET /users", "POST /users", "GET /posts"]);
+
+        let get = |method, uri| {
+            let response = call(&router, request(method, uri, &[]));
+            (response.status(), body(response))
+        };
+        assert_eq!(get(Method::GET, "/users"), (StatusCode::OK, "list".into()));
+        assert_eq!(get(Method::POST, "/users"), (StatusCode::OK, "create".into()));
+        assert_eq!(get(Method::GET, "/posts"), (StatusCode::OK, "list".into()));
+        assert_eq!(get(Method::DELETE, "/users").0, StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(get(Method::GET, "/none").0, StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    #[should_panic = "conflicting route `POST /users` with `GET /users`"]
+    fn boxed_router_conflict() {
+        let _ = Router::new()
+            .route("/users", get(list))
+            .boxed()
+            .route("/users", post(create));
+    }
+}
@@ -0,0 +1,735 @@
+//! static file service
+use bytes::{BufMut, Bytes, BytesMut};
+use futures_core::Stream;
+use std::{
+    convert::Infallible,
+    io::{self, SeekFrom},
+    path::{Component, Path, PathBuf},
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll, ready},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tokio::{
+    fs::File,
+    io::{AsyncRead, AsyncSeekExt, ReadBuf},
+};
+
+use super::{Service, boxed::BoxFuture};
+use crate::{
+    headers::{HeaderMap, HeaderValue},
+    helpers::Range,
+    http::{Method, StatusCode, date::{fmt_http_date, parse_http_date}, percent_decode},
+    middleware::encoding::Encoding,
+    request::Request,
+    response::{Body, IntoResponse, Response},
+    routing::OriginalUri,
+};
+
+/// size of each chunk read from file
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// serve files from a directory
+///
+/// the request path is joined to the directory, path containing `..` is rejected with
+/// `404 Not Found`, and directory is served with its `index.html`
+///
+/// the response has `Content-Type` guessed from file extension, `Last-Modified` and `ETag`,
+/// conditional request is answered with `304 Not Modified`, and `Range` request is answered
+/// with `206 Partial Content`
+///
+/// only `GET` and `HEAD` request is accepted, other method is rejected with
+/// `405 Method Not Allowed`
+///
+/// ```no_run
+/// use beetle::{Router, service::fs::ServeDir};
+///
+/// let router = Router::new()
+///     .nest_service("/assets", ServeDir::new("assets").precompressed_gzip().precompressed_br());
+/// ```
+#[derive(Debug, Clone)]
+pub struct ServeDir {
+    root: Arc<Path>,
+    fallback: Option<Arc<Path>>,
+    precompressed: Precompressed,
+}
+
+impl ServeDir {
+    /// create new [`ServeDir`] which serve files under `root`
+    pub fn new(root: impl AsRef<Path>) -> Self {
+        Self { root: root.as_ref().into(), fallback: None, precompressed: Precompressed::default() }
+    }
+
+    /// serve `.gz` sibling file if exists and client accept gzip encoding
+    pub fn precompressed_gzip(mut self) -> Self {
+        self.precompressed.gzip = true;
+        self
+    }
+
+    /// serve `.br` sibling file if exists and client accept brotli encoding
+    pub fn precompressed_br(mut self) -> Self {
+        self.precompressed.br = true;
+        self
+    }
+
+    /// serve `.zst` sibling file if exists and client accept zstd encoding
+    pub fn precompressed_zstd(mut self) -> Self {
+        self.precompressed.zstd = true;
+        self
+    }
+
+    /// serve given file with `200 OK` when the requested file is not found
+    ///
+    /// this is useful for single page application which handle routing on the client, the
+    /// path is not joined to the directory, e.g: `dist/index.html`
+    pub fn fallback_file(mut self, path: impl AsRef<Path>) -> Self {
+        self.fallback = Some(path.as_ref().into());
+        self
+    }
+}
+
+impl Service<Request> for ServeDir {
+    type Response = Response;
+    type Error = Infallible;
+    type Future = BoxFuture;
+
+    fn call(&self, mut req: Request) -> Self::Future {
+        if !matches!(req.method(), Method::GET | Method::HEAD) {
+            return Box::pin(std::future::ready(Ok(method_not_allowed())));
+        }
+        let Some(path) = resolve(&self.root, req.uri().path()) else {
+            return Box::pin(std::future::ready(Ok(StatusCode::NOT_FOUND.into_response())));
+        };
+
+        // directory without trailing slash is redirected, so relative link in `index.html`
+        // resolved correctly
+        let redirect = match req.uri().path().ends_with('/') {
+            true => None,
+            false => {
+                let uri = req.extensions().get::<OriginalUri>().map_or(req.uri(), |e| &e.0);
+                Some(match uri.query() {
+                    Some(query) => format!("{}/?{query}", uri.path()),
+                    None => format!("{}/", uri.path()),
+                })
+            }
+        };
+
+        let fallback = self.fallback.clone();
+        let precompressed = self.precompressed;
+        let is_head = matches!(req.method(), Method::HEAD);
+        let headers = std::mem::take(req.headers_mut());
+
+        Box::pin(async move {
+            let mut path = path;
+            if tokio::fs::metadata(&path).await.is_ok_and(|e| e.is_dir()) {
+                if let Some(location) = redirect {
+                    return Ok(moved_permanently(location));
+                }
+                path.push("index.html");
+            }
+
+            let res = match open(&path, &headers, precompressed, is_head).await {
+                Err(err) if is_not_found(&err) => match fallback {
+                    Some(fallback) => open(&fallback, &headers, precompressed, is_head).await,
+                    None => Err(err),
+                },
+                res => res,
+            };
+            Ok(res.unwrap_or_else(io_error))
+        })
+    }
+
+    fn methods(&self) -> Option<&[Method]> {
+        Some(&[Method::GET])
+    }
+}
+
+// ===== ServeFile =====
+
+/// serve a single file
+///
+/// any request path is answered with the file, see [`ServeDir`] for the response headers
+///
+/// ```no_run
+/// use beetle::{Router, service::fs::ServeFile};
+///
+/// let router = Router::new().route("/favicon.ico", ServeFile::new("assets/favicon.ico"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct ServeFile {
+    path: Arc<Path>,
+    precompressed: Precompressed,
+}
+
+impl ServeFile {
+    /// create new [`ServeFile`]
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self { path: path.as_ref().into(), precompressed: Precompressed::default() }
+    }
+
+    /// serve `.gz` sibling file if exists and client accept gzip encoding
+    pub fn precompressed_gzip(mut self) -> Self {
+        self.precompressed.gzip = true;
+        self
+    }
+
+    /// serve `.br` sibling file if exists and client accept brotli encoding
+    pub fn precompressed_br(mut self) -> Self {
+        self.precompressed.br = true;
+        self
+    }
+
+    /// serve `.zst` sibling file if exists and client accept zstd encoding
+    pub fn precompressed_zstd(mut self) -> Self {
+        self.precompressed.zstd = true;
+        self
+    }
+}
+
+impl Service<Request> for ServeFile {
+    type Response = Response;
+    type Error = Infallible;
+    type Future = BoxFuture;
+
+    fn call(&self, mut req: Request) -> Self::Future {
+        if !matches!(req.method(), Method::GET | Method::HEAD) {
+            return Box::pin(std::future::ready(Ok(method_not_allowed())));
+        }
+        let path = self.path.clone();
+        let precompressed = self.precompressed;
+        let is_head = matches!(req.method(), Method::HEAD);
+        let headers = std::mem::take(req.headers_mut());
+
+        Box::pin(async move {
+            let res = open(&path, &headers, precompressed, is_head).await;
+            Ok(res.unwrap_or_else(io_error))
+        })
+    }
+
+    fn methods(&self) -> Option<&[Method]> {
+        Some(&[Method::GET])
+    }
+}
+
+// ===== Response =====
+
+/// enabled precompressed sibling files
+#[derive(Debug, Clone, Copy, Default)]
+struct Precompressed {
+    gzip: bool,
+    br: bool,
+    zstd: bool,
+}
+
+impl Precompressed {
+    fn is_enabled(&self) -> bool {
+        self.gzip || self.br || self.zstd
+    }
+
+    /// returns the sibling file extension, or [`None`] if the encoding is disabled
+    fn extension(&self, encoding: Encoding) -> Option<&'static str> {
+        match encoding {
+            Encoding::Gzip if self.gzip => Some("gz"),
+            Encoding::Br if self.br => Some("br"),
+            Encoding::Zstd if self.zstd => Some("zst"),
+            _ => None,
+        }
+    }
+}
+
+/// open file and build the response
+async fn open(
+    path: &Path,
+    headers: &HeaderMap,
+    precompressed: Precompressed,
+    is_head: bool,
+) -> io::Result<Response> {
+    let meta = tokio::fs::metadata(path).await?;
+    if !meta.is_file() {
+        return Err(io::ErrorKind::NotFound.into());
+    }
+
+    let mut res = Response::default();
+    let res_headers = res.headers_mut();
+    res_headers.insert("content-type", HeaderValue::from_static(content_type(path)));
+    res_headers.insert("accept-ranges", HeaderValue::from_static("bytes"));
+
+    let (mut file, meta) = match precompressed.is_enabled() {
+        true => {
+            res_headers.append("vary", HeaderValue::from_static("accept-encoding"));
+            let mut available = Vec::with_capacity(3);
+            for encoding in Encoding::PREFERENCE {
+                let Some(ext) = precompressed.extension(encoding) else {
+                    continue;
+                };
+                let mut sibling = path.as_os_str().to_owned();
+                sibling.push(".");
+                sibling.push(ext);
+                let sibling = PathBuf::from(sibling);
+                if tokio::fs::metadata(&sibling).await.is_ok_and(|e| e.is_file()) {
+                    available.push((encoding, sibling));
+                }
+            }
+            let accept = headers.get_all("accept-encoding").filter_map(|e| e.as_str().ok());
+            let variant = Encoding::negotiate(accept, |e| available.iter().any(|(a, _)| *a == e))
+                .and_then(|e| available.into_iter().find(|(a, _)| *a == e));
+            match variant {
+                Some((encoding, sibling)) => {
+                    res_headers.insert("content-encoding", HeaderValue::from_static(encoding.as_str()));
+                    let file = File::open(sibling).await?;
+                    let meta = file.metadata().await?;
+                    (file, meta)
+                }
+                None => (File::open(path).await?, meta),
+            }
+        }
+        false => (File::open(path).await?, meta),
+    };
+
+    let len = meta.len();
+    let modified = meta.modified().ok();
+    let etag = modified.map(|modified| {
+        let secs = modified.duration_since(UNIX_EPOCH).map(|e| e.as_secs()).unwrap_or(0);
+        format!("\"{secs:x}-{len:x}\"")
+    });
+    if let Some(modified) = modified {
+        res_headers.insert("last-modified", HeaderValue::from_string(fmt_http_date(modified)));
+    }
+    if let Some(etag) = &etag {
+        res_headers.insert("etag", HeaderValue::from_string(etag.clone()));
+    }
+
+    if is_not_modified(headers, etag.as_deref(), modified) {
+        *res.status_mut() = StatusCode::NOT_MODIFIED;
+        return Ok(res);
+    }
+
+    let range = match is_head {
+        true => Range::default(),
+        false => Range::from_headers(headers),
+    };
+
+    // single range is served by seeking the file instead of discarding the preceding bytes
+    if range.is_fresh(res.headers())
+        && let Some(ranges) = range.ranges(len)
+        && let [range] = &ranges[..]
+    {
+        file.seek(SeekFrom::Start(range.start)).await?;
+        *res.status_mut() = StatusCode::PARTIAL_CONTENT;
+        let content_range = format!("bytes {}-{}/{len}", range.start, range.end - 1);
+        res.headers_mut().insert("content-range", HeaderValue::from_string(content_range));
+
+        let remaining = range.end - range.start;
+        let (parts, _) = res.into_parts();
+        let body = Body::sized_stream(remaining as usize, FileStream::new(file, remaining));
+        return Ok(Response::from_parts(parts, body));
+    }
+
+    let (parts, _) = res.into_parts();
+    let body = Body::sized_stream(len as usize, FileStream::new(file, len));
+    Ok(range.respond(Response::from_parts(parts, body)))
+}
+
+/// returns `true` if the conditional request should be answered with `304 Not Modified`
+///
+/// `If-Modified-Since` is ignored if `If-None-Match` is present
+fn is_not_modified(headers: &HeaderMap, etag: Option<&str>, modified: Option<SystemTime>) -> bool {
+    if let Some(value) = headers.get("if-none-match") {
+        let Ok(value) = value.as_str() else {
+            return false;
+        };
+        return value
+            .split(',')
+            .map(str::trim)
+            .any(|e| e == "*" || etag.is_some_and(|etag| weak_eq(e, etag)));
+    }
+
+    let since = headers
+        .get("if-modified-since")
+        .and_then(|e| e.as_str().ok())
+        .and_then(parse_http_date);
+    match (since, modified) {
+        (Some(since), Some(modified)) => unix_secs(modified) <= unix_secs(since),
+        _ => false,
+    }
+}
+
+/// weak comparison of entity tag
+fn weak_eq(a: &str, b: &str) -> bool {
+    a.trim_start_matches("W/") == b.trim_start_matches("W/")
+}
+
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map(|e| e.as_secs()).unwrap_or(0)
+}
+
+fn method_not_allowed() -> Response {
+    let mut res = StatusCode::METHOD_NOT_ALLOWED.into_response();
+    res.headers_mut().insert("allow", HeaderValue::from_static("GET, HEAD"));
+    res
+}
+
+fn moved_permanently(location: String) -> Response {
+    let Ok(location) = HeaderValue::try_from_string(location) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let mut res = StatusCode::MOVED_PERMANENTLY.into_response();
+    res.headers_mut().insert("location", location);
+    res
+}
+
+fn is_not_found(err: &io::Error) -> bool {
+    matches!(err.kind(), io::ErrorKind::NotFound | io::ErrorKind::NotADirectory)
+}
+
+fn io_error(err: io::Error) -> Response {
+    match err.kind() {
+        _ if is_not_found(&err) => StatusCode::NOT_FOUND.into_response(),
+        io::ErrorKind::PermissionDenied => StatusCode::FORBIDDEN.into_response(),
+        _ => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    }
+}
+
+// ===== Path =====
+
+/// join percent decoded request path to `root`
+///
+/// returns [`None`] if the path contains `..` or segment that is not a plain file name
+fn resolve(root: &Path, path: &str) -> Option<PathBuf> {
+    let decoded = percent_decode(path);
+    let mut buf = root.to_path_buf();
+    for segment in decoded.split('/') {
+        if segment.is_empty() || segment == "." {
+            continue;
+        }
+        if segment.contains(['\\', '\0']) {
+            return None;
+        }
+        let mut components = Path::new(segment).components();
+        let (Some(Component::Normal(_)), None) = (components.next(), components.next()) else {
+            return None;
+        };
+        buf.push(segment);
+    }
+    Some(buf)
+}
+
+/// guess content type from file extension
+fn content_type(path: &Path) -> &'static str {
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or_default().to_ascii_lowercase();
+    match ext.as_str() {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "js" | "mjs" => "text/javascript; charset=utf-8",
+        "txt" => "text/plain; charset=utf-8",
+        "md" => "text/markdown; charset=utf-8",
+        "csv" => "text/csv; charset=utf-8",
+        "xml" => "text/xml; charset=utf-8",
+        "json" | "map" => "application/json",
+        "webmanifest" => "application/manifest+json",
+        "wasm" => "application/wasm",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "gz" => "application/gzip",
+        "tar" => "application/x-tar",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "ico" => "image/x-icon",
+        "bmp" => "image/bmp",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "mp3" => "audio/mpeg",
+        "ogg" => "audio/ogg",
+        "wav" => "audio/wav",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        _ => "application/octet-stream",
+    }
+}
+
+// ===== Stream =====
+
+/// stream the remaining bytes of a file
+struct FileStream {
+    file: File,
+    remaining: u64,
+    /// read buffer, each chunk is split from it, the capacity is reused when the previous
+    /// chunks are dropped
+    buffer: BytesMut,
+}
+
+impl FileStream {
+    fn new(file: File, remaining: u64) -> Self {
+        Self { file, remaining, buffer: BytesMut::new() }
+    }
+}
+
+impl Stream for FileStream {
+    type Item = io::Result<Bytes>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let me = self.get_mut();
+        if me.remaining == 0 {
+            return Poll::Ready(None);
+        }
+        let len = me.remaining.min(CHUNK_SIZE as u64) as usize;
+        // does not allocate when the capacity is left from previous pending read
+        me.buffer.reserve(len);
+
+        let mut buf = ReadBuf::uninit(&mut me.buffer.spare_capacity_mut()[..len]);
+        ready!(Pin::new(&mut me.file).poll_read(cx, &mut buf))?;
+
+        let read = buf.filled().len();
+        if read == 0 {
+            me.remaining = 0;
+            return Poll::Ready(Some(Err(io::ErrorKind::UnexpectedEof.into())));
+        }
+        // SAFETY: `read` bytes is initialized by `poll_read`
+        unsafe { me.buffer.advance_mut(read) };
+        me.remaining -= read as u64;
+        Poll::Ready(Some(Ok(me.buffer.split().freeze())))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::service::HttpService;
+
+    #[test]
+    fn resolve_path() {
+        let root = Path::new("public");
+        assert_eq!(resolve(root, "/"), Some(PathBuf::from("public")));
+        assert_eq!(resolve(root, "/css/app.css"), Some(PathBuf::from("public/css/app.css")));
+        assert_eq!(resolve(root, "/a//./b/"), Some(PathBuf::from("public/a/b")));
+        assert_eq!(resolve(root, "/hello%20world.txt"), Some(PathBuf::from("public/hello world.txt")));
+        assert_eq!(resolve(root, "/../secret"), None);
+        assert_eq!(resolve(root, "/a/../../secret"), None);
+        assert_eq!(resolve(root, "/%2e%2e/secret"), None);
+        assert_eq!(resolve(root, "/a%2F..%2F..%2Fsecret"), None);
+        assert_eq!(resolve(root, "/..%5Csecret"), None);
+        assert_eq!(resolve(root, "/a%00b"), None);
+    }
+
+    #[test]
+    fn conditional() {
+        let modified = UNIX_EPOCH + std::time::Duration::from_secs(784111777);
+        let etag = Some("\"2ebc0b21-10\"");
+        let check = |name: &'static str, value: &'static str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(name, HeaderValue::from_static(value));
+            is_not_modified(&headers, etag, Some(modified))
+        };
+
+        assert!(check("if-none-match", "\"2ebc0b21-10\""));
+        assert!(check("if-none-match", "\"foo\", W/\"2ebc0b21-10\""));
+        assert!(check("if-none-match", "*"));
+        assert!(!check("if-none-match", "\"foo\""));
+        assert!(check("if-modified-since", "Sun, 06 Nov 1994 08:49:37 GMT"));
+        assert!(check("if-modified-since", "Mon, 07 Nov 1994 08:49:37 GMT"));
+        assert!(!check("if-modified-since", "Sat, 05 Nov 1994 08:49:37 GMT"));
+        assert!(!check("if-modified-since", "invalid"));
+        assert!(!is_not_modified(&HeaderMap::new(), etag, Some(modified)));
+    }
+
+    /// temporary directory removed on drop
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let root = std::env::temp_dir().join(format!("beetle-fs-{name}-{}", std::process::id()));
+            let _ = std::fs::remove_dir_all(&root);
+            std::fs::create_dir_all(root.join("docs")).unwrap();
+            for (path, content) in [
+                ("hello.txt", "hello world"),
+                ("hello.txt.gz", "gzip"),
+                ("hello.txt.br", "brotli"),
+                ("index.html", "<app>"),
+                ("docs/index.html", "<docs>"),
+            ] {
+                std::fs::write(root.join(path), content).unwrap();
+            }
+            Self(root)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// call the service and collect the response body
+    fn get(
+        service: &impl HttpService,
+        uri: &'static str,
+        headers: &[(&'static str, &'static str)],
+    ) -> (Response, Bytes) {
+        use crate::testing::request;
+
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+        runtime.block_on(async {
+            let Ok(res) = service.call(request(Method::GET, uri, headers)).await;
+            let (parts, mut body) = res.into_parts();
+            let mut collected = Vec::new();
+            while let Some(data) = std::future::poll_fn(|cx| body.poll_data(cx)).await {
+                collected.extend_from_slice(&data.unwrap());
+            }
+            (Response::from_parts(parts, Body::empty()), collected.into())
+        })
+    }
+
+    fn header<'a>(res: &'a Response, name: &str) -> Option<&'a str> {
+        res.headers().get(name).and_then(|value| value.as_str().ok())
+    }
+
+    #[test]
+    fn serve_file() {
+        let dir = TempDir::new("serve");
+        let service = ServeDir::new(&dir.0);
+
+        let (res, body) = get(&service, "/hello.txt", &[]);
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(body, "hello world");
+        assert_eq!(header(&res, "content-type"), Some("text/plain; charset=utf-8"));
+        assert_eq!(header(&res, "vary"), None);
+
+        let (res, _) = get(&service, "/missing.txt", &[]);
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+        let service = ServeFile::new(dir.0.join("hello.txt"));
+        let (_, body) = get(&service, "/any/path", &[]);
+        assert_eq!(body, "hello world");
+    }
+
+    #[test]
+    fn large_file() {
+        let dir = TempDir::new("large");
+        let content: Vec<u8> = (0..CHUNK_SIZE * 3 + 7).map(|i| i as u8).collect();
+        std::fs::write(dir.0.join("large.bin"), &content).unwrap();
+        let service = ServeDir::new(&dir.0);
+
+        let (res, body) = get(&service, "/large.bin", &[]);
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(body, content);
+
+        let (res, body) = get(&service, "/large.bin", &[("range", "bytes=65530-131080")]);
+        assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(body, content[65530..=131080]);
+    }
+
+    #[test]
+    fn not_modified() {
+        let dir = TempDir::new("not-modified");
+        let service = ServeDir::new(&dir.0);
+
+        let (res, _) = get(&service, "/hello.txt", &[]);
+        let etag = header(&res, "etag").unwrap().to_owned().leak();
+        let modified = header(&res, "last-modified").unwrap().to_owned().leak();
+
+        let (res, body) = get(&service, "/hello.txt", &[("if-none-match", etag)]);
+        assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(header(&res, "etag"), Some(&*etag));
+        assert!(body.is_empty());
+
+        let (res, _) = get(&service, "/hello.txt", &[("if-modified-since", modified)]);
+        assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+
+        let (res, body) = get(&service, "/hello.txt", &[("if-none-match", "\"other\"")]);
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(body, "hello world");
+    }
+
+    #[test]
+    fn range() {
+        let dir = TempDir::new("range");
+        let service = ServeDir::new(&dir.0);
+
+        let (res, body) = get(&service, "/hello.txt", &[("range", "bytes=6-")]);
+        assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(header(&res, "content-range"), Some("bytes 6-10/11"));
+        assert_eq!(body, "world");
+
+        let (res, body) = get(&service, "/hello.txt", &[("range", "bytes=0-1, 6-7")]);
+        assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT);
+        assert!(header(&res, "content-type").unwrap().starts_with("multipart/byteranges; boundary="));
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(body.contains("bytes 0-1/11\r\n\r\nhe\r\n"));
+        assert!(body.contains("bytes 6-7/11\r\n\r\nwo\r\n"));
+        assert!(body.contains("text/plain; charset=utf-8"));
+
+        let (res, body) = get(&service, "/hello.txt", &[("range", "bytes=20-")]);
+        assert_eq!(res.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(header(&res, "content-range"), Some("bytes */11"));
+        assert!(body.is_empty());
+    }
+
+    #[test]
+    fn precompressed() {
+        let dir = TempDir::new("precompressed");
+        let service = ServeDir::new(&dir.0).precompressed_gzip().precompressed_br();
+
+        let (res, body) = get(&service, "/hello.txt", &[("accept-encoding", "gzip")]);
+        assert_eq!(header(&res, "content-encoding"), Some("gzip"));
+        assert_eq!(header(&res, "vary"), Some("accept-encoding"));
+        // content type of the original file
+        assert_eq!(header(&res, "content-type"), Some("text/plain; charset=utf-8"));
+        assert_eq!(body, "gzip");
+
+        let (res, body) = get(&service, "/hello.txt", &[("accept-encoding", "gzip, br")]);
+        assert_eq!(header(&res, "content-encoding"), Some("br"));
+        assert_eq!(body, "brotli");
+
+        let (res, body) = get(&service, "/hello.txt", &[]);
+        assert_eq!(header(&res, "content-encoding"), None);
+        assert_eq!(header(&res, "vary"), Some("accept-encoding"));
+        assert_eq!(body, "hello world");
+
+        // disabled encoding is not served
+        let service = ServeDir::new(&dir.0).precompressed_gzip();
+        let (res, body) = get(&service, "/hello.txt", &[("accept-encoding", "br")]);
+        assert_eq!(header(&res, "content-encoding"), None);
+        assert_eq!(body, "hello world");
+    }
+
+    #[test]
+    fn directory() {
+        let dir = TempDir::new("directory");
+        let service = ServeDir::new(&dir.0);
+
+        let (res, _) = get(&service, "/docs?page=1", &[]);
+        assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+        assert_eq!(header(&res, "location"), Some("/docs/?page=1"));
+
+        let (res, body) = get(&service, "/docs/", &[]);
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(header(&res, "content-type"), Some("text/html; charset=utf-8"));
+        assert_eq!(body, "<docs>");
+
+        let (_, body) = get(&service, "/", &[]);
+        assert_eq!(body, "<app>");
+    }
+
+    #[test]
+    fn fallback_file() {
+        let dir = TempDir::new("fallback");
+        let service = ServeDir::new(&dir.0).fallback_file(dir.0.join("index.html"));
+
+        let (res, body) = get(&service, "/users/42", &[]);
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(body, "<app>");
+
+        // existing file is served as is
+        let (_, body) = get(&service, "/hello.txt", &[]);
+        assert_eq!(body, "hello world");
+
+        let (res, _) = get(&ServeDir::new(&dir.0), "/users/42", &[]);
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -5,7 +5,7 @@ use super::Service;
 use crate::{
     futures::{EitherInto, FutureExt},
     helpers::Either,
-    http::StatusCode,
+    http::{Method, StatusCode},
     request::Request,
     response::{IntoResponse, Response},
 };
@@ -27,6 +27,13 @@ where
             Either::Right(r) => r.call(req).right_into(),
         }
     }
+
+    fn methods(&self) -> Option<&[Method]> {
+        match self {
+            Either::Left(l) => l.methods(),
+            Either::Right(r) => r.methods(),
+        }
+    }
 }
 
 macro_rules! status_service {
@@ -1,9 +1,9 @@
-use bytes::{Buf, Bytes, BytesMut};
-use memchr::memmem::{self, FindIter, Finder, find_iter};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
 use std::{
     hint, io,
     pin::Pin,
-    sync::Arc,
+    sync::{Arc, Mutex, PoisonError},
+    time::Duration,
     task::{
         Context,
         Poll::{self, Ready},
@@ -15,59 +15,287 @@ use super::HttpService;
 use crate::{
     common::ByteStr,
     headers::{HeaderMap, HeaderValue},
-    http::{Method, Version},
+    http::{Method, StatusCode, Uri, Version, is_tchar},
     io::{StreamReadExt, StreamWriteExt},
     net::Socket,
-    request::{self, Parts, Request},
-    response::{self, IntoResponse},
+    request::{self, Coding, Parts, Reader, Request, Timeout},
+    response::{self, IntoResponse, Response},
+    runtime::{Runtime, Shutdown, Watcher},
     service::Service,
 };
 
-fn to_io<E: Into<Box<dyn std::error::Error + Send + Sync>>>(err: E) -> io::Error {
-    io::Error::new(io::ErrorKind::InvalidData, err)
+#[cfg(feature = "log")]
+use crate::ext::FmtExt;
+
+mod error;
+
+pub use error::ParseError;
+
+fn parse_str(val: &[u8], err: ParseError) -> Result<&str, ParseError> {
+    std::str::from_utf8(val).map_err(|_| err)
+}
+
+/// `Content-Length = 1*DIGIT`
+fn parse_content_len(val: &[u8]) -> Result<usize, ParseError> {
+    if val.is_empty() || !val.iter().all(u8::is_ascii_digit) {
+        return Err(ParseError::InvalidContentLength);
+    }
+    parse_str(val, ParseError::InvalidContentLength)?
+        .parse()
+        .map_err(|_| ParseError::InvalidContentLength)
+}
+
+/// Check a `Transfer-Encoding` field line, `chunked` is set once `chunked` coding is found.
+///
+/// Multiple field lines are combined as a single list. Only `chunked` is supported, and it can
+/// only be applied once.
+fn parse_transfer_encoding(val: &[u8], chunked: &mut bool) -> Result<(), ParseError> {
+    let val = parse_str(val, ParseError::InvalidHeader)?;
+    let mut codings = val.split(',').map(str::trim).filter(|coding| !coding.is_empty()).peekable();
+    if codings.peek().is_none() {
+        return Err(ParseError::InvalidTransferCoding);
+    }
+    for coding in codings {
+        if !coding.eq_ignore_ascii_case("chunked") {
+            return Err(ParseError::UnsupportedTransferCoding);
+        }
+        if *chunked {
+            return Err(ParseError::InvalidTransferCoding);
+        }
+        *chunked = true;
+    }
+    Ok(())
+}
+
+fn parse_header(val: &[u8]) -> Result<HeaderValue, ParseError> {
+    HeaderValue::try_copy_from_slice(val).map_err(|_| ParseError::InvalidHeader)
+}
+
+/// Callback to respond to malformed request.
+type OnParseError = Arc<dyn Fn(ParseError) -> Response + Send + Sync>;
+
+/// Http connection configuration.
+#[derive(Clone)]
+pub struct Config {
+    keep_alive: bool,
+    keep_alive_timeout: Option<Duration>,
+    max_requests: Option<usize>,
+    header_timeout: Option<Duration>,
+    body_timeout: Option<Duration>,
+    write_timeout: Option<Duration>,
+    max_request_line: usize,
+    max_header_size: usize,
+    max_headers: usize,
+    max_head_size: usize,
+    on_parse_error: Option<OnParseError>,
+    pub(crate) shutdown_timeout: Option<Duration>,
 }
 
-fn parse_str(val: &[u8]) -> Result<&str, io::Error> {
-    std::str::from_utf8(val).map_err(to_io)
+impl Config {
+    /// Create default [`Config`].
+    ///
+    /// - keep-alive enabled
+    /// - 60 seconds idle keep-alive timeout
+    /// - unlimited requests per connection
+    /// - 30 seconds request head, body read and write timeout
+    /// - 8 KiB request line and per header size, 100 headers and 64 KiB total request head
+    /// - 30 seconds graceful shutdown timeout
+    pub const fn new() -> Self {
+        Self {
+            keep_alive: true,
+            keep_alive_timeout: Some(Duration::from_secs(60)),
+            max_requests: None,
+            header_timeout: Some(Duration::from_secs(30)),
+            body_timeout: Some(Duration::from_secs(30)),
+            write_timeout: Some(Duration::from_secs(30)),
+            max_request_line: 8 * 1024,
+            max_header_size: 8 * 1024,
+            max_headers: 100,
+            max_head_size: 64 * 1024,
+            on_parse_error: None,
+            shutdown_timeout: Some(Duration::from_secs(30)),
+        }
+    }
+
+    /// Set whether connection is kept alive after a response.
+    pub fn keep_alive(mut self, enabled: bool) -> Self {
+        self.keep_alive = enabled;
+        self
+    }
+
+    /// Set how long an idle connection is kept alive waiting for the next request.
+    ///
+    /// `None` means idle connection is never closed by the server.
+    pub fn keep_alive_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.keep_alive_timeout = timeout;
+        self
+    }
+
+    /// Set maximum number of requests served on a single connection.
+    pub fn max_requests(mut self, max: usize) -> Self {
+        self.max_requests = Some(max);
+        self
+    }
+
+    /// Set how long to wait for the full request head.
+    ///
+    /// On timeout, `408 Request Timeout` is sent and the connection is closed.
+    pub fn header_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.header_timeout = timeout;
+        self
+    }
+
+    /// Set how long to wait for each read of the request body.
+    ///
+    /// On timeout, `408 Request Timeout` is sent and the connection is closed.
+    pub fn body_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.body_timeout = timeout;
+        self
+    }
+
+    /// Set how long to wait for each write of the response.
+    ///
+    /// On timeout, the connection is closed.
+    pub fn write_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.write_timeout = timeout;
+        self
+    }
+
+    /// Set maximum request line length in bytes.
+    ///
+    /// Longer request line is rejected with `414 URI Too Long`.
+    pub fn max_request_line(mut self, max: usize) -> Self {
+        self.max_request_line = max;
+        self
+    }
+
+    /// Set maximum size of a single header line in bytes.
+    ///
+    /// Larger header is rejected with `431 Request Header Fields Too Large`.
+    pub fn max_header_size(mut self, max: usize) -> Self {
+        self.max_header_size = max;
+        self
+    }
+
+    /// Set maximum number of headers.
+    ///
+    /// More headers is rejected with `431 Request Header Fields Too Large`.
+    pub fn max_headers(mut self, max: usize) -> Self {
+        self.max_headers = max;
+        self
+    }
+
+    /// Set maximum size of the whole request head, including request line, in bytes.
+    ///
+    /// Larger request head is rejected with `431 Request Header Fields Too Large`.
+    pub fn max_head_size(mut self, max: usize) -> Self {
+        self.max_head_size = max;
+        self
+    }
+
+    /// Set how malformed request is responded.
+    ///
+    /// By default, the response is the [`ParseError::status`] with the error message as body.
+    /// The connection is always closed after the response is written.
+    pub fn on_parse_error<F>(mut self, f: F) -> Self
+    where
+        F: Fn(ParseError) -> Response + Send + Sync + 'static,
+    {
+        self.on_parse_error = Some(Arc::new(f));
+        self
+    }
+
+    /// Returns the response for malformed request.
+    fn parse_error(&self, err: ParseError) -> Response {
+        match &self.on_parse_error {
+            Some(f) => f(err),
+            None => err.into_response(),
+        }
+    }
+
+    /// Set how long graceful shutdown waits for connections to finish.
+    ///
+    /// `None` means waiting until all connections are closed.
+    pub fn shutdown_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.shutdown_timeout = timeout;
+        self
+    }
 }
 
-fn parse_int(val: &[u8]) -> Result<usize, io::Error> {
-    parse_str(val)?.parse().map_err(to_io)
+impl Default for Config {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-fn parse_header(val: &[u8]) -> Result<HeaderValue, io::Error> {
-    HeaderValue::try_copy_from_slice(val).map_err(to_io)
+impl std::fmt::Debug for Config {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Config")
+            .field("keep_alive", &self.keep_alive)
+            .field("keep_alive_timeout", &self.keep_alive_timeout)
+            .field("max_requests", &self.max_requests)
+            .field("header_timeout", &self.header_timeout)
+            .field("body_timeout", &self.body_timeout)
+            .field("write_timeout", &self.write_timeout)
+            .field("max_request_line", &self.max_request_line)
+            .field("max_header_size", &self.max_header_size)
+            .field("max_headers", &self.max_headers)
+            .field("max_head_size", &self.max_head_size)
+            .field("on_parse_error", &self.on_parse_error.is_some())
+            .field("shutdown_timeout", &self.shutdown_timeout)
+            .finish()
+    }
 }
 
 #[derive(Debug, Clone)]
-pub struct TcpService<S> {
+pub struct TcpService<S, T> {
     inner: S,
+    config: Config,
+    sleep: fn(Duration) -> T,
+    shutdown: Shutdown,
 }
 
-impl<S> TcpService<S> {
-    pub fn new(inner: S) -> TcpService<S> {
-        TcpService { inner }
+impl<S, T> TcpService<S, T> {
+    pub fn new<R: Runtime<Sleep = T>>(inner: S, config: Config) -> TcpService<S, T> {
+        TcpService { inner, config, sleep: R::sleep, shutdown: Shutdown::default() }
+    }
+
+    pub(crate) fn with_shutdown(mut self, shutdown: Shutdown) -> Self {
+        self.shutdown = shutdown;
+        self
     }
 }
 
-impl<S> Service<Socket> for TcpService<S>
+impl<S, T> Service<Socket> for TcpService<S, T>
 where
     S: HttpService + Clone,
+    T: Future<Output = ()> + Send + 'static,
 {
     type Response = ();
 
     type Error = ();
 
-    type Future = TcpFuture<S, S::Future>;
+    type Future = TcpFuture<S, S::Future, T>;
 
     fn call(&self, io: Socket) -> Self::Future {
         #[cfg(feature = "log")]
         log::trace!("connection open");
         TcpFuture {
             inner: self.inner.clone(),
+            config: self.config.clone(),
+            sleep: self.sleep,
+            shutdown: self.shutdown.watch(),
             buffer: BytesMut::with_capacity(1024),
             res_buffer: BytesMut::with_capacity(1024),
             io: Arc::new(io),
+            requests: 0,
+            version: Version::V11,
+            is_head: false,
+            keep_alive: true,
+            res_chunked: false,
+            res_remaining: None,
+            reader: None,
+            timer: self.config.header_timeout.map(self.sleep),
             phase: TcpPhase::Read,
         }
     }
@@ -82,72 +310,200 @@ pin_project_lite::pin_project! {
         Inner { #[pin] future: Fut },
         ResponseData { body: response::Body },
         Write { body: response::Body, data: Bytes },
+        Flush,
+        Drain,
         Cleanup,
     }
 }
 
 pin_project_lite::pin_project! {
     #[project = TcpProject]
-    pub struct TcpFuture<S,F> {
+    pub struct TcpFuture<S,F,T> {
         inner: S,
+        config: Config,
+        sleep: fn(Duration) -> T,
+        shutdown: Watcher,
         buffer: BytesMut,
         res_buffer: BytesMut,
         io: Arc<Socket>,
+        // number of requests served
+        requests: usize,
+        // version of current request
+        version: Version,
+        // whether current request method is `HEAD`
+        is_head: bool,
+        // whether connection is kept alive after current response
+        keep_alive: bool,
+        // whether current response body is chunked
+        res_chunked: bool,
+        // remaining length of current response body, if the length is known
+        res_remaining: Option<usize>,
+        // body reader of current request
+        reader: Option<Arc<Mutex<Reader>>>,
+        // idle keep-alive, request head or write timer
+        #[pin]
+        timer: Option<T>,
         #[pin]
         phase: TcpPhase<F>,
     }
 }
 
-impl<S> TcpFuture<S,S::Future>
+impl<S,T> TcpFuture<S,S::Future,T>
 where
     S: Service<Request>,
     S::Response: IntoResponse,
     S::Error: IntoResponse,
+    T: Future<Output = ()> + Send + 'static,
 {
     fn try_poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
         use TcpPhaseProject::*;
 
         let TcpProject {
             inner,
+            config,
+            sleep,
+            shutdown,
             buffer,
             res_buffer,
             io,
+            requests,
+            version: req_version,
+            is_head,
+            keep_alive,
+            res_chunked,
+            res_remaining,
+            reader,
+            mut timer,
             mut phase,
         } = self.as_mut().project();
 
+        // respond without calling the inner service and close the connection
+        macro_rules! reject {
+            ($response:expr) => {{
+                *keep_alive = false;
+                *res_chunked = false;
+                let body = write_reject(res_buffer, $response.into_response());
+                *res_remaining = body.content_len();
+                phase.set(TcpPhase::ResponseData { body });
+                continue;
+            }};
+        }
+
+        macro_rules! parse_error {
+            ($err:expr) => {{
+                let err: ParseError = $err;
+                #[cfg(feature = "log")]
+                log::debug!("{err}");
+                reject!(config.parse_error(err))
+            }};
+        }
+
+        macro_rules! parse {
+            ($result:expr) => {
+                match $result {
+                    Ok(ok) => ok,
+                    Err(err) => parse_error!(err),
+                }
+            };
+        }
+
         loop {
             match phase.as_mut().project() {
                 Read => {
+                    shutdown.register(cx);
+                    if shutdown.is_closing() && buffer.is_empty() {
+                        return Ready(Ok(()));
+                    }
+
+                    if let Some(timeout) = timer.as_mut().as_pin_mut()
+                        && timeout.poll(cx).is_ready()
+                    {
+                        if buffer.is_empty() {
+                            #[cfg(feature = "log")]
+                            log::trace!("keep-alive timeout");
+                            return Ready(Ok(()));
+                        }
+
+                        #[cfg(feature = "log")]
+                        log::trace!("request head timeout");
+                        reject!(StatusCode::REQUEST_TIMEOUT);
+                    }
+
+                    let is_idle = buffer.is_empty();
                     let read = ready!(io.poll_read_buf(cx, buffer)?);
                     if read == 0 {
                         return Ready(Ok(()));
                     }
+                    if is_idle {
+                        timer.set(config.header_timeout.map(*sleep));
+                    }
                     phase.set(TcpPhase::Parse);
                 }
                 Parse => {
-                    let Some((method, path, version, header_offset)) = parse_request_line(buffer)? else {
+                    let Some((method, path, version, header_offset)) = parse!(parse_request_line(buffer)) else {
+                        if buffer.len() > config.max_request_line {
+                            parse_error!(ParseError::UriTooLong);
+                        }
                         let _ = buffer.try_reclaim(1024);
                         phase.set(TcpPhase::Read);
                         continue;
                     };
 
+                    if header_offset > config.max_request_line {
+                        parse_error!(ParseError::UriTooLong);
+                    }
+
                     let headers = &buffer[header_offset..];
 
                     let mut parser = HeaderParser::new(headers);
                     let mut header_map = HeaderMap::new();
-                    let mut content_len = 0;
+                    let mut content_len = None;
+                    let mut chunked = false;
 
-                    for result in &mut parser {
-                        let (key,val) = result;
+                    let mut count = 0;
+
+                    let result = parser.try_for_each(|result| {
+                        let (key,val) = result?;
+
+                        // `key`, `": "`, `val`
+                        count += 1;
+                        if count > config.max_headers || key.len() + 2 + val.len() > config.max_header_size {
+                            return Err(ParseError::HeaderTooLarge);
+                        }
 
                         if key.eq_ignore_ascii_case(b"content-length") {
-                            content_len = parse_int(val)?;
+                            let len = parse_content_len(val)?;
+                            // conflicting length may be interpreted differently by intermediary
+                            if content_len.is_some_and(|prev| prev != len) {
+                                return Err(ParseError::AmbiguousLength);
+                            }
+                            content_len = Some(len);
+                        }
+
+                        if key.eq_ignore_ascii_case(b"transfer-encoding") {
+                            // HTTP/1.0 does not define transfer coding
+                            if matches!(version, Version::V10) {
+                                return Err(ParseError::InvalidTransferCoding);
+                            }
+                            parse_transfer_encoding(val, &mut chunked)?;
                         }
 
                         // TODO: prevent copy
-                        let name = parse_str(key)?;
+                        // header name is case-insensitive, it is normalized to lowercase
+                        let name = parse_str(key, ParseError::InvalidHeader)?.to_ascii_lowercase();
                         let value = parse_header(val)?;
-                        header_map.append(ByteStr::copy_from_str(name), value);
+                        header_map.append(ByteStr::from(name), value);
+                        Ok(())
+                    });
+                    parse!(result);
+
+                    let head_size = header_offset + parser.offset();
+                    let pending_line = buffer.len() - head_size;
+
+                    if head_size > config.max_head_size
+                        || (!parser.complete() && pending_line > config.max_header_size)
+                    {
+                        parse_error!(ParseError::HeaderTooLarge);
                     }
 
                     if !parser.complete() {
@@ -155,31 +511,61 @@ where
                         continue;
                     }
 
+                    // request smuggling, intermediary may use either one to delimit the body
+                    if chunked && content_len.is_some() {
+                        parse_error!(ParseError::AmbiguousLength);
+                    }
+
                     let body_offset = parser.offset();
 
+                    timer.set(None);
+
                     let path_ptr = (path.as_ptr(), path.len());
                     let request_line = buffer.split_to(header_offset).freeze();
 
                     // `buffer` now contains [headers..,body..]
 
                     // SAFETY: `buffer.split_to` will not move pointer and path was a `str`
-                    let path = unsafe {
+                    let target = unsafe {
                         let path = std::slice::from_raw_parts(path_ptr.0, path_ptr.1);
                         ByteStr::from_utf8_unchecked(request_line.slice_ref(path))
                     };
 
+                    let uri = parse!(Uri::try_from_shared(target).map_err(|_| ParseError::InvalidTarget));
+
+                    parse!(check_target(&method, &uri));
+
                     // `body_offset` is offset started from `header_offset`,
                     // but `buffer` is already started from `header_offset`
                     buffer.advance(body_offset);
 
-                    // `buffer` now contains [body..]
+                    // `buffer` now contains [body..], and maybe subsequent pipelined request
 
-                    let body = buffer.split();
+                    *requests += 1;
+                    *req_version = version;
+                    *is_head = matches!(method, Method::HEAD);
+                    *keep_alive = config.keep_alive
+                        && config.max_requests.is_none_or(|max| *requests < max)
+                        && match version {
+                            Version::V10 => has_connection(&header_map, "keep-alive"),
+                            _ => !has_connection(&header_map, "close"),
+                        };
+                    // chunked transfer coding is not understood by HTTP/1.0 client
+                    *res_chunked = !matches!(version, Version::V10);
+
+                    let parts = Parts::new(method, uri, version, header_map, <_>::default());
+                    let coding = match chunked {
+                        true => Coding::Chunked,
+                        false => Coding::Length(content_len.unwrap_or_default()),
+                    };
+                    let timeout = config.body_timeout.map(|timeout| Timeout::new(timeout, *sleep));
+                    let body_reader = Reader::new(coding, io.clone(), buffer.split(), timeout);
+                    let body_reader = Arc::new(Mutex::new(body_reader));
+                    let body = request::Body::new(coding, body_reader.clone());
+                    *reader = Some(body_reader);
 
-                    // `buffer` now empty
+                    // `buffer` now empty, the rest is returned after body is drained
 
-                    let parts = Parts::new(method, path, version, header_map, <_>::default());
-                    let body = request::Body::new(content_len, Some(io.clone()), body.freeze());
                     let request = Request::from_parts(parts, body);
 
                     let future = inner.call(request);
@@ -187,41 +573,168 @@ where
                 }
                 Inner { future } => {
                     let mut response = ready!(future.poll(cx)).into_response();
+
+                    if let Some(body_reader) = reader.as_ref() {
+                        let body_reader = body_reader.lock().unwrap_or_else(PoisonError::into_inner);
+
+                        if body_reader.is_timed_out() {
+                            #[cfg(feature = "log")]
+                            log::trace!("request body timeout");
+                            response = StatusCode::REQUEST_TIMEOUT.into_response();
+                            *keep_alive = false;
+                        }
+
+                        // the rest of the body is not worth to drain
+                        if body_reader.is_exceeded() || !body_reader.is_drainable() {
+                            *keep_alive = false;
+                        }
+                    }
+
                     response::validate(&mut response);
-                    let (parts,body) = response.into_parts();
+                    let (mut parts,body) = response.into_parts();
+
+                    if shutdown.is_closing() || has_connection(parts.headers(), "close") {
+                        *keep_alive = false;
+                    }
+
+                    // headers is kept as if the body is written
+                    let skip_body = *is_head
+                        || matches!(parts.status(), StatusCode::NO_CONTENT | StatusCode::NOT_MODIFIED);
+
+                    if body.content_len().is_none() && !*res_chunked {
+                        // without chunked transfer coding, body is delimited by closing the
+                        // connection
+                        parts.headers_mut().remove("transfer-encoding");
+                        if !skip_body {
+                            *keep_alive = false;
+                        }
+                    }
+
+                    if skip_body || body.content_len().is_some() {
+                        *res_chunked = false;
+                    }
+
+                    if !*keep_alive {
+                        parts.headers_mut().insert("connection", HeaderValue::from_static("close"));
+                    } else if matches!(req_version, Version::V10) {
+                        parts.headers_mut().insert("connection", HeaderValue::from_static("keep-alive"));
+                    }
+
                     response::write(&parts, res_buffer);
+
+                    if skip_body {
+                        timer.set(config.write_timeout.map(*sleep));
+                        phase.set(TcpPhase::Flush);
+                        continue;
+                    }
+
+                    *res_remaining = body.content_len();
                     phase.set(TcpPhase::ResponseData { body });
                 }
                 ResponseData { body } => {
-                    let data = ready!(body.poll_data(cx)?);
+                    let Some(mut data) = ready!(body.poll_data(cx)?) else {
+                        if res_remaining.is_some_and(|remaining| remaining != 0) {
+                            // the message cannot be completed, closing the connection let the
+                            // client know the response is incomplete
+                            return Ready(Err(io::Error::new(
+                                io::ErrorKind::UnexpectedEof,
+                                "response body is shorter than its content length",
+                            )));
+                        }
+                        if *res_chunked {
+                            res_buffer.put_slice(b"0\r\n\r\n");
+                        }
+                        timer.set(config.write_timeout.map(*sleep));
+                        phase.set(TcpPhase::Flush);
+                        continue;
+                    };
+                    if let Some(remaining) = res_remaining.as_mut() {
+                        if data.len() > *remaining {
+                            // excess data is not written, and the connection is closed as the
+                            // rest of the stream cannot be framed
+                            #[cfg(feature = "log")]
+                            log::error!("response body is longer than its content length");
+                            data.truncate(*remaining);
+                            *body = response::Body::empty();
+                            *keep_alive = false;
+                        }
+                        *remaining -= data.len();
+                    }
+                    if data.is_empty() {
+                        continue;
+                    }
+                    if *res_chunked {
+                        write_chunk_size(res_buffer, data.len());
+                    }
                     let TcpReplace::ResponseData { body } = phase.as_mut().project_replace(TcpPhase::Cleanup) else {
                         // SAFETY: we are in match arm of it
                         unsafe { hint::unreachable_unchecked() }
                     };
+                    timer.set(config.write_timeout.map(*sleep));
                     phase.set(TcpPhase::Write { body, data });
                 },
-                Write { body, data } => {
+                Write { body: _, data } => {
+                    // response head and chunk size is written along with the data, the next
+                    // data is only polled after this one is written
+                    poll_write_timeout(timer.as_mut(), cx)?;
+                    ready!(io.poll_write_all(cx, res_buffer)?);
                     ready!(io.poll_write_all(cx, data)?);
 
-                    if body.is_end_stream() {
-                        phase.set(TcpPhase::Cleanup);
-                    } else {
-                        let TcpReplace::Write { body, data } = phase.as_mut().project_replace(TcpPhase::Cleanup) else {
-                            // SAFETY: we are in match arm of it
-                            unsafe { hint::unreachable_unchecked() }
-                        };
-                        debug_assert!(data.is_empty());
-                        phase.set(TcpPhase::ResponseData { body });
+                    if *res_chunked {
+                        res_buffer.put_slice(b"\r\n");
                     }
+
+                    let TcpReplace::Write { body, data } = phase.as_mut().project_replace(TcpPhase::Cleanup) else {
+                        // SAFETY: we are in match arm of it
+                        unsafe { hint::unreachable_unchecked() }
+                    };
+                    debug_assert!(data.is_empty());
+                    phase.set(TcpPhase::ResponseData { body });
+                },
+                Flush => {
+                    poll_write_timeout(timer.as_mut(), cx)?;
+                    ready!(io.poll_write_all(cx, res_buffer)?);
+                    phase.set(TcpPhase::Drain);
+                },
+                Drain => {
+                    if !*keep_alive {
+                        return Ready(Ok(()));
+                    }
+
+                    if let Some(shared) = reader.as_ref() {
+                        // request body is still held somewhere else, it is unknown where the
+                        // next request starts
+                        if Arc::strong_count(shared) > 1 {
+                            return Ready(Ok(()));
+                        }
+
+                        let mut body_reader = shared.lock().unwrap_or_else(PoisonError::into_inner);
+                        if !ready!(body_reader.poll_drain(cx)?) {
+                            // the rest of the body is not worth to drain
+                            return Ready(Ok(()));
+                        }
+                        // unconsumed bytes is the start of the next request
+                        *buffer = body_reader.take_buffer();
+                    }
+
+                    *reader = None;
+                    phase.set(TcpPhase::Cleanup);
                 },
                 Cleanup => {
                     // this state will make sure all shared buffer is dropped
                     res_buffer.clear();
-                    buffer.clear();
 
                     buffer.reserve(1024);
                     res_buffer.reserve(1024);
 
+                    // pipelined request is already in buffer
+                    if !buffer.is_empty() {
+                        timer.set(config.header_timeout.map(*sleep));
+                        phase.set(TcpPhase::Parse);
+                        continue;
+                    }
+
+                    timer.set(config.keep_alive_timeout.map(*sleep));
                     phase.set(TcpPhase::Read);
                 },
             }
@@ -230,11 +743,12 @@ where
 }
 
 
-impl<S> Future for TcpFuture<S, S::Future>
+impl<S,T> Future for TcpFuture<S, S::Future, T>
 where
     S: Service<Request>,
     S::Response: IntoResponse,
     S::Error: IntoResponse,
+    T: Future<Output = ()> + Send + 'static,
 {
     type Output = Result<(), ()>;
 
@@ -254,6 +768,44 @@ where
     }
 }
 
+/// Returns error if write timer is elapsed.
+fn poll_write_timeout<T: Future<Output = ()>>(
+    timer: Pin<&mut Option<T>>,
+    cx: &mut Context,
+) -> io::Result<()> {
+    match timer.as_pin_mut().map(|sleep| sleep.poll(cx)) {
+        Some(Ready(())) => Err(io::Error::new(io::ErrorKind::TimedOut, "timeout writing response")),
+        _ => Ok(()),
+    }
+}
+
+/// Write response head that is not from the inner service, the connection is closed afterwards.
+///
+/// Body with unknown length is delimited by closing the connection.
+fn write_reject(res_buffer: &mut BytesMut, mut response: Response) -> response::Body {
+    response::validate(&mut response);
+    let (mut parts, body) = response.into_parts();
+    parts.headers_mut().remove("transfer-encoding");
+    parts.headers_mut().insert("connection", HeaderValue::from_static("close"));
+    response::write(&parts, res_buffer);
+    body
+}
+
+/// Returns `true` if `Connection` header contains given option.
+fn has_connection(headers: &HeaderMap, option: &str) -> bool {
+    headers
+        .get_all("connection")
+        .filter_map(|val| val.as_str().ok())
+        .flat_map(|val| val.split(','))
+        .any(|val| val.trim().eq_ignore_ascii_case(option))
+}
+
+/// write chunk size line of chunked transfer coding
+fn write_chunk_size(buf: &mut BytesMut, len: usize) {
+    use std::fmt::Write;
+    let _ = write!(buf, "{len:X}\r\n");
+}
+
 // ===== Parser =====
 
 /// Returns (method, path, version, header offset)
@@ -263,7 +815,7 @@ where
 /// incomplete and cannot be converted back into `BytesMut` without copy.
 fn parse_request_line(
     buf: &[u8],
-) -> io::Result<Option<(Method, &str, Version, usize)>> {
+) -> Result<Option<(Method, &str, Version, usize)>, ParseError> {
     let mut offset = 0;
 
     macro_rules! collect_until {
@@ -286,23 +838,18 @@ fn parse_request_line(
     // NOTE: method
 
     let method = collect_until!(e => e.is_ascii_whitespace());
-    let method = match method {
-        b"GET" | b"get" => Method::GET,
-        b"POST" | b"post" => Method::POST,
-        b"PUT" | b"put" => Method::PUT,
-        b"PATCH" | b"patch" => Method::PUT,
-        b"DELETE" | b"delete" => Method::DELETE,
-        b"HEAD" | b"head" => Method::HEAD,
-        b"CONNECT" | b"connect" => Method::CONNECT,
-        _ => return Err(to_io(format!("unknown method: {method:?}"))),
-    };
+    let method = Method::from_bytes(method).map_err(|_| {
+        #[cfg(feature = "log")]
+        log::trace!("invalid method: {:?}", method.lossy());
+        ParseError::InvalidMethod
+    })?;
 
     collect_until!(e => !e.is_ascii_whitespace());
 
     // NOTE: path
 
     let path = collect_until!(e => e.is_ascii_whitespace());
-    let path = parse_str(path)?;
+    let path = parse_str(path, ParseError::InvalidTarget)?;
 
     collect_until!(e => !e.is_ascii_whitespace());
 
@@ -313,21 +860,58 @@ fn parse_request_line(
         b"HTTP/1.0" => Version::V10,
         b"HTTP/1.1" => Version::V11,
         b"HTTP/2" => Version::V2,
-        _ => return Err(to_io(format!("unknown http version: {version:?}"))),
+        [b'H', b'T', b'T', b'P', b'/', major, b'.', minor]
+            if major.is_ascii_digit() && minor.is_ascii_digit() =>
+        {
+            return Err(ParseError::UnsupportedVersion);
+        }
+        [b'H', b'T', b'T', b'P', b'/', major] if major.is_ascii_digit() => {
+            return Err(ParseError::UnsupportedVersion);
+        }
+        _ => {
+            #[cfg(feature = "log")]
+            log::trace!("invalid http version: {:?}", version.lossy());
+            return Err(ParseError::InvalidVersion);
+        }
     };
 
-    match memmem::find(&buf[offset..], b"\r\n") {
-        Some(ok) => Ok(Some((method, path, version, offset + ok + b"\r\n".len()))),
-        None => Ok(None),
+    // request line is terminated right after the version, bare LF is rejected
+    match &buf[offset..] {
+        [b'\r', b'\n', ..] => Ok(Some((method, path, version, offset + b"\r\n".len()))),
+        [] | [b'\r'] => Ok(None),
+        _ => Err(ParseError::InvalidVersion),
+    }
+}
+
+/// authority-form is only used for `CONNECT`, and asterisk-form is only used for `OPTIONS`,
+/// as defined in RFC 9112 section 3.2
+fn check_target(method: &Method, uri: &Uri) -> Result<(), ParseError> {
+    if uri.is_authority_form() != matches!(method, Method::CONNECT)
+        || uri.is_asterisk() && !matches!(method, Method::OPTIONS)
+    {
+        return Err(ParseError::InvalidTarget);
+    }
+    Ok(())
+}
+
+/// `OWS` as defined in RFC 9110 section 5.6.3
+fn trim_ows(mut val: &[u8]) -> &[u8] {
+    while let [b' ' | b'\t', rest @ ..] = val {
+        val = rest;
+    }
+    while let [rest @ .., b' ' | b'\t'] = val {
+        val = rest;
     }
+    val
 }
 
+/// Header field lines tokenizer, as defined in RFC 9112 section 5.
+///
+/// `field-name ":" OWS field-value OWS CRLF`, field line folding and bare LF is rejected.
 struct HeaderParser<'a> {
     buf: &'a [u8],
     offset: usize,
     complete: bool,
-    colsp: Finder<'static>,
-    iter: FindIter<'a, 'static>,
 }
 
 impl<'a> HeaderParser<'a> {
@@ -336,8 +920,6 @@ impl<'a> HeaderParser<'a> {
             buf,
             offset: 0,
             complete: false,
-            colsp: Finder::new(b": "),
-            iter: find_iter(buf, b"\r\n")
         }
     }
 
@@ -351,29 +933,468 @@ impl<'a> HeaderParser<'a> {
 }
 
 impl<'a> Iterator for HeaderParser<'a> {
-    type Item = (&'a [u8], &'a [u8]);
+    type Item = Result<(&'a [u8], &'a [u8]), ParseError>;
 
     fn next(&mut self) -> Option<Self::Item> {
         if self.complete {
             return None;
         }
 
-        let cr = self.iter.next()?;
-        let kv = self.buf.get(self.offset..cr)?;
+        let rest = &self.buf[self.offset..];
+        let lf = memchr::memchr(b'\n', rest)?;
+
+        let Some(line) = rest[..lf].strip_suffix(b"\r") else {
+            return Some(Err(ParseError::InvalidHeader));
+        };
 
-        if kv.is_empty() {
+        self.offset += lf + 1;
+
+        if line.is_empty() {
             self.complete = true;
             return None;
         }
 
-        let colsp = self.colsp.find(kv)?;
+        // field name is a token, which also rejects obs-fold and whitespace before colon
+        let Some(colon) = line.iter().position(|&b| b == b':') else {
+            return Some(Err(ParseError::InvalidHeader));
+        };
 
-        let key = kv.get(..colsp)?;
-        let val = kv.get(colsp + 1..)?;
+        let key = &line[..colon];
+        let val = trim_ows(&line[colon + 1..]);
 
-        self.offset = cr + 2;
+        if key.is_empty() || !key.iter().copied().all(is_tchar) {
+            return Some(Err(ParseError::InvalidHeader));
+        }
 
-        Some((key, val))
+        Some(Ok((key, val)))
     }
 }
 
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn request_line_error(input: &[u8]) -> ParseError {
+        parse_request_line(input).unwrap_err()
+    }
+
+    #[test]
+    fn request_line() {
+        let (method, path, version, offset) = parse_request_line(b"GET /a HTTP/1.1\r\nHost").unwrap().unwrap();
+        assert!(matches!(method, Method::GET));
+        assert_eq!(path, "/a");
+        assert!(matches!(version, Version::V11));
+        assert_eq!(offset, 17);
+
+        assert!(parse_request_line(b"GET /a HTTP/1.1").unwrap().is_none());
+
+        let (method, ..) = parse_request_line(b"PATCH / HTTP/1.1\r\n").unwrap().unwrap();
+        assert_eq!(method, Method::PATCH);
+
+        // method is case-sensitive
+        let (method, ..) = parse_request_line(b"get / HTTP/1.1\r\n").unwrap().unwrap();
+        assert_eq!(method, Method::Extension("get".into()));
+        assert_eq!(method.as_str(), "get");
+    }
+
+    #[test]
+    fn request_line_error_status() {
+        assert_eq!(request_line_error(b"G(T / HTTP/1.1\r\n"), ParseError::InvalidMethod);
+        assert_eq!(request_line_error(b"GET / HTTP/3.0\r\n"), ParseError::UnsupportedVersion);
+        assert_eq!(request_line_error(b"GET / FOO\r\n"), ParseError::InvalidVersion);
+        assert_eq!(request_line_error(b"GET / HTTP/1.1\n"), ParseError::InvalidVersion);
+        assert_eq!(request_line_error(b"GET /\xff HTTP/1.1\r\n"), ParseError::InvalidTarget);
+
+        assert_eq!(ParseError::UnsupportedTransferCoding.status(), StatusCode::NOT_IMPLEMENTED);
+        assert_eq!(ParseError::UnsupportedVersion.status(), StatusCode::HTTP_VERSION_NOT_SUPPORTED);
+        assert_eq!(ParseError::InvalidHeader.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn request_target() {
+        let check = |method, target| check_target(&method, &Uri::from_static(target));
+        assert!(check(Method::OPTIONS, "*").is_ok());
+        assert!(check(Method::CONNECT, "example.com:443").is_ok());
+        assert!(check(Method::GET, "/").is_ok());
+        assert!(check(Method::GET, "http://example.com/").is_ok());
+
+        assert_eq!(check(Method::GET, "*"), Err(ParseError::InvalidTarget));
+        assert_eq!(check(Method::GET, "example.com:443"), Err(ParseError::InvalidTarget));
+        assert_eq!(check(Method::CONNECT, "/"), Err(ParseError::InvalidTarget));
+    }
+
+    #[test]
+    fn header_error() {
+        let mut parser = HeaderParser::new(b"Host: a\r\nbroken\r\n\r\n");
+        assert_eq!(parser.next(), Some(Ok((&b"Host"[..], &b"a"[..]))));
+        assert_eq!(parser.next(), Some(Err(ParseError::InvalidHeader)));
+    }
+
+    type Field<'a> = (&'a [u8], &'a [u8]);
+
+    fn headers(input: &[u8]) -> Result<Vec<Field<'_>>, ParseError> {
+        let mut parser = HeaderParser::new(input);
+        let headers = parser.by_ref().collect::<Result<Vec<_>, _>>()?;
+        assert!(parser.complete());
+        assert_eq!(parser.offset(), input.len());
+        Ok(headers)
+    }
+
+    #[test]
+    fn header_ows() {
+        let parsed = headers(b"Host:example.com\r\nA:\t b \t\r\nEmpty:\r\n\r\n").unwrap();
+        assert_eq!(parsed, [
+            (&b"Host"[..], &b"example.com"[..]),
+            (&b"A"[..], &b"b"[..]),
+            (&b"Empty"[..], &b""[..]),
+        ]);
+    }
+
+    #[test]
+    fn header_rejected() {
+        // obs-fold
+        assert_eq!(headers(b"A: b\r\n c\r\n\r\n"), Err(ParseError::InvalidHeader));
+        // whitespace before colon
+        assert_eq!(headers(b"Host : a\r\n\r\n"), Err(ParseError::InvalidHeader));
+        // bare LF
+        assert_eq!(headers(b"Host: a\n\r\n"), Err(ParseError::InvalidHeader));
+        // non token name
+        assert_eq!(headers(b"H(st: a\r\n\r\n"), Err(ParseError::InvalidHeader));
+        assert_eq!(headers(b": a\r\n\r\n"), Err(ParseError::InvalidHeader));
+    }
+
+    /// status line of the first response
+    #[cfg(feature = "tokio")]
+    fn status_line(config: &Config, input: &str) -> String {
+        use crate::{service::servicefn::service_fn, testing::serve};
+        use std::{convert::Infallible, future::ready};
+
+        let service = service_fn(|_| ready(Ok::<_, Infallible>(Response::default())));
+        let output = serve(service, config.clone(), input.as_bytes());
+        let output = String::from_utf8_lossy(&output);
+        output.lines().next().unwrap_or_default().to_owned()
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn head_limits() {
+        let a = |n: usize| "a".repeat(n);
+        let close = "connection: close\r\n";
+
+        // request line, including CRLF
+        let config = Config::new().max_request_line(32);
+        let line = |n: usize| format!("GET /{} HTTP/1.1\r\n", a(n - 16));
+        assert_eq!(status_line(&config, &format!("{}{close}\r\n", line(32))), "HTTP/1.1 200 OK");
+        assert_eq!(status_line(&config, &format!("{}{close}\r\n", line(33))), "HTTP/1.1 414 URI Too Long");
+        // incomplete request line
+        assert_eq!(status_line(&config, &format!("GET /{}", a(40))), "HTTP/1.1 414 URI Too Long");
+
+        // single header line, excluding CRLF
+        let config = Config::new().max_header_size(32);
+        let header = |n: usize| format!("x: {}\r\n", a(n - 3));
+        let too_large = "HTTP/1.1 431 Request Header Fields Too Large";
+        let request = |header| format!("GET / HTTP/1.1\r\n{header}{close}\r\n");
+        assert_eq!(status_line(&config, &request(header(32))), "HTTP/1.1 200 OK");
+        assert_eq!(status_line(&config, &request(header(33))), too_large);
+        // incomplete header line
+        assert_eq!(status_line(&config, &format!("GET / HTTP/1.1\r\nx: {}", a(40))), too_large);
+
+        // header count
+        let config = Config::new().max_headers(2);
+        assert_eq!(status_line(&config, &request("a: 1\r\n".into())), "HTTP/1.1 200 OK");
+        assert_eq!(status_line(&config, &request("a: 1\r\nb: 2\r\n".into())), too_large);
+
+        // the whole request head
+        let config = Config::new().max_head_size(64);
+        assert_eq!(status_line(&config, &request(header(10))), "HTTP/1.1 200 OK");
+        assert_eq!(status_line(&config, &request(header(10).repeat(3))), too_large);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn on_parse_error() {
+        let status = StatusCode::IM_A_TEAPOT;
+        let config = Config::new().on_parse_error(move |err| match err {
+            ParseError::InvalidMethod => status.into_response(),
+            err => err.into_response(),
+        });
+        assert_eq!(status_line(&config, "G(T / HTTP/1.1\r\n\r\n"), "HTTP/1.1 418 I'm a teapot");
+        assert_eq!(status_line(&config, "GET / FOO\r\n\r\n"), "HTTP/1.1 400 Bad Request");
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn keep_alive() {
+        use crate::{service::servicefn::service_fn, testing::serve};
+        use std::{convert::Infallible, future::ready};
+
+        let service = service_fn(|_| ready(Ok::<_, Infallible>(Response::default())));
+        let serve = |config: Config, input: &str| {
+            let output = serve(service.clone(), config, input.as_bytes());
+            String::from_utf8_lossy(&output).into_owned()
+        };
+        let responses = |output: &str| output.matches("200 OK").count();
+        let next = "GET / HTTP/1.1\r\nconnection: close\r\n\r\n";
+
+        // HTTP/1.1 is kept alive unless `connection: close`
+        let output = serve(Config::new(), &format!("GET / HTTP/1.1\r\n\r\n{next}"));
+        assert_eq!(responses(&output), 2);
+        assert_eq!(output.matches("connection: close\r\n").count(), 1);
+        let output = serve(Config::new(), &format!("GET / HTTP/1.1\r\nconnection: close\r\n\r\n{next}"));
+        assert_eq!(responses(&output), 1);
+        assert!(output.contains("connection: close\r\n"));
+
+        // HTTP/1.0 is closed unless `connection: keep-alive`
+        let output = serve(Config::new(), &format!("GET / HTTP/1.0\r\n\r\n{next}"));
+        assert_eq!(responses(&output), 1);
+        assert!(output.contains("connection: close\r\n"));
+        let output = serve(Config::new(), &format!("GET / HTTP/1.0\r\nconnection: keep-alive\r\n\r\n{next}"));
+        assert_eq!(responses(&output), 2);
+        assert!(output.contains("connection: keep-alive\r\n"));
+
+        // disabled keep-alive
+        let output = serve(Config::new().keep_alive(false), &format!("GET / HTTP/1.1\r\n\r\n{next}"));
+        assert_eq!(responses(&output), 1);
+
+        // idle connection is closed after the keep-alive timeout
+        let config = Config::new().keep_alive_timeout(Some(Duration::from_millis(50)));
+        let output = serve(config, "GET / HTTP/1.1\r\n\r\n");
+        assert_eq!(responses(&output), 1);
+        assert!(!output.contains("connection: close\r\n"));
+
+        // the last allowed request is responded with `connection: close`
+        let output = serve(Config::new().max_requests(2), &"GET / HTTP/1.1\r\n\r\n".repeat(3));
+        assert_eq!(responses(&output), 2);
+        assert_eq!(output.matches("connection: close\r\n").count(), 1);
+        assert!(output.ends_with("connection: close\r\n\r\n"));
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn timeouts() {
+        use crate::{
+            response::Body,
+            service::servicefn::service_fn,
+            testing::{connect, read_to_end, serve},
+        };
+        use futures_core::Stream;
+        use std::{convert::Infallible, task::{Context, Poll}};
+        use tokio::io::AsyncWriteExt;
+
+        let timeout = Some(Duration::from_millis(50));
+
+        // stalled request head
+        let config = Config::new().header_timeout(timeout);
+        assert_eq!(status_line(&config, "GET / HTTP/1.1\r\nhost: a\r\n"), "HTTP/1.1 408 Request Timeout");
+
+        // stalled request body
+        let service = service_fn(|req: Request| async move {
+            let status = match req.into_body().collect().await {
+                Ok(_) => StatusCode::OK,
+                Err(_) => StatusCode::BAD_REQUEST,
+            };
+            Ok::<_, Infallible>(status.into_response())
+        });
+        let config = Config::new().body_timeout(timeout);
+        let output = serve(service, config, b"POST / HTTP/1.1\r\ncontent-length: 10\r\n\r\nabc");
+        let output = String::from_utf8_lossy(&output);
+        assert!(output.starts_with("HTTP/1.1 408 Request Timeout\r\n"));
+        assert!(output.contains("connection: close\r\n"));
+
+        // stalled response write, the body never ends so the connection can only be closed by
+        // the write timeout
+        struct Endless;
+
+        impl Stream for Endless {
+            type Item = io::Result<Bytes>;
+
+            fn poll_next(self: Pin<&mut Self>, _: &mut Context) -> Poll<Option<Self::Item>> {
+                Poll::Ready(Some(Ok(Bytes::from_static(&[b'a'; 64 * 1024]))))
+            }
+        }
+
+        let service = service_fn(|_| std::future::ready(Ok::<_, Infallible>(Response::new(Body::stream(Endless)))));
+        let config = Config::new().write_timeout(timeout);
+        let output = connect(service, config, async |mut client| {
+            client.write_all(b"GET / HTTP/1.1\r\n\r\n").await.unwrap();
+            // client does not read the response
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            read_to_end(&mut client).await
+        });
+        assert!(output.starts_with(b"HTTP/1.1 200 OK\r\n"));
+    }
+
+    #[test]
+    fn content_length() {
+        assert_eq!(parse_content_len(b"42"), Ok(42));
+        assert_eq!(parse_content_len(b"+42"), Err(ParseError::InvalidContentLength));
+        assert_eq!(parse_content_len(b"4 2"), Err(ParseError::InvalidContentLength));
+        assert_eq!(parse_content_len(b""), Err(ParseError::InvalidContentLength));
+    }
+
+    #[test]
+    fn transfer_encoding() {
+        let parse = |lines: &[&[u8]]| {
+            let mut chunked = false;
+            lines.iter().try_for_each(|line| parse_transfer_encoding(line, &mut chunked))?;
+            Ok(chunked)
+        };
+        assert_eq!(parse(&[b"chunked"]), Ok(true));
+        assert_eq!(parse(&[b"Chunked"]), Ok(true));
+        assert_eq!(parse(&[b" , chunked"]), Ok(true));
+        assert_eq!(parse(&[b"gzip, chunked"]), Err(ParseError::UnsupportedTransferCoding));
+        assert_eq!(parse(&[b"chunked, gzip"]), Err(ParseError::UnsupportedTransferCoding));
+        assert_eq!(parse(&[b"gzip"]), Err(ParseError::UnsupportedTransferCoding));
+        assert_eq!(parse(&[b"chunked, chunked"]), Err(ParseError::InvalidTransferCoding));
+        assert_eq!(parse(&[b""]), Err(ParseError::InvalidTransferCoding));
+
+        // multiple field lines is a single list
+        assert_eq!(parse(&[b"gzip", b"chunked"]), Err(ParseError::UnsupportedTransferCoding));
+        assert_eq!(parse(&[b"chunked", b"chunked"]), Err(ParseError::InvalidTransferCoding));
+
+        assert_eq!(ParseError::InvalidTransferCoding.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(ParseError::UnsupportedTransferCoding.status(), StatusCode::NOT_IMPLEMENTED);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn transfer_encoding_rejected() {
+        let config = Config::new();
+        let bad_request = "HTTP/1.1 400 Bad Request";
+        let body = "\r\n5\r\nhello\r\n0\r\n\r\n";
+
+        let input = format!("POST / HTTP/1.1\r\ntransfer-encoding: chunked, chunked\r\n{body}");
+        assert_eq!(status_line(&config, &input), bad_request);
+
+        let input = format!("POST / HTTP/1.1\r\ntransfer-encoding: chunked\r\ntransfer-encoding: chunked\r\n{body}");
+        assert_eq!(status_line(&config, &input), bad_request);
+
+        let input = format!("POST / HTTP/1.0\r\ntransfer-encoding: chunked\r\n{body}");
+        assert_eq!(status_line(&config, &input), bad_request);
+
+        let not_implemented = "HTTP/1.1 501 Not Implemented";
+        let input = format!("POST / HTTP/1.1\r\ntransfer-encoding: gzip, chunked\r\n{body}");
+        assert_eq!(status_line(&config, &input), not_implemented);
+
+        let input = format!("POST / HTTP/1.1\r\ntransfer-encoding: gzip\r\ntransfer-encoding: chunked\r\n{body}");
+        assert_eq!(status_line(&config, &input), not_implemented);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn sized_body_length() {
+        use crate::{response::Body, service::servicefn::service_fn, testing::{serve, stream}};
+        use std::{convert::Infallible, future::ready};
+
+        fn respond(len: usize) -> impl HttpService + Clone {
+            service_fn(move |_| {
+                let body = Body::sized_stream(len, stream(&["hello", " world"]));
+                ready(Ok::<_, Infallible>(Response::new(body)))
+            })
+        }
+        let responses = |output: &[u8]| {
+            let output = String::from_utf8_lossy(output).into_owned();
+            (output.matches("HTTP/1.1 200 OK").count(), output)
+        };
+        let input = b"GET / HTTP/1.1\r\n\r\nGET / HTTP/1.1\r\nconnection: close\r\n\r\n";
+
+        let (count, output) = responses(&serve(respond(11), Config::new(), input));
+        assert_eq!(count, 2);
+        assert!(output.ends_with("\r\n\r\nhello world"));
+
+        // shorter body close the connection before the next request
+        let (count, output) = responses(&serve(respond(20), Config::new(), input));
+        assert_eq!(count, 1);
+        assert!(output.contains("content-length: 20\r\n"));
+        assert!(output.ends_with("\r\n\r\nhello world"));
+
+        // excess data is not written
+        let (count, output) = responses(&serve(respond(5), Config::new(), input));
+        assert_eq!(count, 1);
+        assert!(output.contains("content-length: 5\r\n"));
+        assert!(output.ends_with("\r\n\r\nhello"));
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn skip_body() {
+        use crate::{response::Body, service::servicefn::service_fn, testing::{serve, stream}};
+        use std::{convert::Infallible, future::ready};
+
+        let service = service_fn(|req: Request| {
+            let mut response = match req.path().as_str() {
+                "/stream" => Response::new(Body::stream(stream(&["hello"]))),
+                _ => Response::new(Body::bytes("hello")),
+            };
+            match req.path().as_str() {
+                "/no-content" => *response.status_mut() = StatusCode::NO_CONTENT,
+                "/not-modified" => {
+                    // length of the representation that would have been sent
+                    *response.status_mut() = StatusCode::NOT_MODIFIED;
+                    response.headers_mut().insert("content-length", HeaderValue::from_static("5"));
+                }
+                _ => {}
+            }
+            ready(Ok::<_, Infallible>(response))
+        });
+        let serve = |input: &str| {
+            let output = serve(service.clone(), Config::new(), input.as_bytes());
+            String::from_utf8_lossy(&output).into_owned()
+        };
+        let close = "GET / HTTP/1.1\r\nconnection: close\r\n\r\n";
+
+        // headers is kept as if the body is written, the connection is kept alive
+        let output = serve(&format!("HEAD / HTTP/1.1\r\n\r\n{close}"));
+        assert_eq!(output.matches("HTTP/1.1 200 OK").count(), 2);
+        assert_eq!(output.matches("content-length: 5\r\n").count(), 2);
+        assert_eq!(output.matches("hello").count(), 1);
+        assert!(output.ends_with("\r\n\r\nhello"));
+
+        let output = serve(&format!("HEAD /stream HTTP/1.1\r\n\r\n{close}"));
+        assert!(output.contains("transfer-encoding: chunked\r\n"));
+        assert_eq!(output.matches("hello").count(), 1);
+
+        let output = serve(&format!("GET /no-content HTTP/1.1\r\n\r\n{close}"));
+        assert!(output.starts_with("HTTP/1.1 204 No Content\r\n"));
+        assert_eq!(output.matches("content-length").count(), 1);
+        assert_eq!(output.matches("hello").count(), 1);
+
+        let output = serve(&format!("GET /not-modified HTTP/1.1\r\n\r\n{close}"));
+        assert!(output.starts_with("HTTP/1.1 304 Not Modified\r\n"));
+        assert_eq!(output.matches("content-length: 5\r\n").count(), 2);
+        assert_eq!(output.matches("hello").count(), 1);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn drain_body() {
+        use crate::{service::servicefn::service_fn, testing::serve};
+        use std::{convert::Infallible, future::ready};
+
+        // request body is never read
+        let service = service_fn(|_| ready(Ok::<_, Infallible>(Response::default())));
+        let serve = |input: &[u8]| {
+            let output = serve(service.clone(), Config::new(), input);
+            String::from_utf8_lossy(&output).into_owned()
+        };
+        let next = "GET / HTTP/1.1\r\nconnection: close\r\n\r\n";
+
+        let output = serve(format!("POST / HTTP/1.1\r\ncontent-length: 5\r\n\r\nhello{next}").as_bytes());
+        assert_eq!(output.matches("HTTP/1.1 200 OK").count(), 2);
+
+        let chunked = "POST / HTTP/1.1\r\ntransfer-encoding: chunked\r\n\r\n5\r\nhello\r\n0\r\n\r\n";
+        let output = serve(format!("{chunked}{next}").as_bytes());
+        assert_eq!(output.matches("HTTP/1.1 200 OK").count(), 2);
+
+        // body larger than the drain limit is not drained
+        let output = serve(format!("POST / HTTP/1.1\r\ncontent-length: 1000000\r\n\r\nhello{next}").as_bytes());
+        assert_eq!(output.matches("HTTP/1.1 200 OK").count(), 1);
+        assert!(output.contains("connection: close\r\n"));
+
+        // unterminated chunked body is drained until the limit, then the connection is closed
+        let chunk = format!("400\r\n{}\r\n", "a".repeat(1024));
+        let output = serve(format!("POST / HTTP/1.1\r\ntransfer-encoding: chunked\r\n\r\n{}", chunk.repeat(100)).as_bytes());
+        assert!(output.matches("HTTP/1.1 200 OK").count() <= 1);
+    }
+}
@@ -0,0 +1,74 @@
+use crate::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+
+/// An error when parsing request head.
+///
+/// The connection responds with [`ParseError::status`] and closes afterwards. The response
+/// can be customized with [`Config::on_parse_error`][super::Config::on_parse_error].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseError {
+    /// Method is not a valid token.
+    InvalidMethod,
+    /// Request target is not a valid uri for the method.
+    InvalidTarget,
+    /// Version is malformed, or request line is not terminated with CRLF.
+    InvalidVersion,
+    /// Version is well formed but not supported.
+    UnsupportedVersion,
+    /// Header field is malformed.
+    InvalidHeader,
+    /// `Content-Length` is not a valid length.
+    InvalidContentLength,
+    /// Conflicting `Content-Length`, or both `Content-Length` and `Transfer-Encoding` present.
+    AmbiguousLength,
+    /// Transfer coding other than `chunked` is applied.
+    UnsupportedTransferCoding,
+    /// `chunked` is applied more than once, `Transfer-Encoding` is empty, or it is sent with
+    /// HTTP/1.0.
+    InvalidTransferCoding,
+    /// Request line exceeds the limit.
+    UriTooLong,
+    /// Header field, header count, or the whole request head exceeds the limit.
+    HeaderTooLarge,
+}
+
+impl ParseError {
+    /// Returns the response status code for the error.
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Self::UnsupportedTransferCoding => StatusCode::NOT_IMPLEMENTED,
+            Self::UnsupportedVersion => StatusCode::HTTP_VERSION_NOT_SUPPORTED,
+            Self::UriTooLong => StatusCode::URI_TOO_LONG,
+            Self::HeaderTooLarge => StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE,
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::InvalidMethod => "invalid method",
+            Self::InvalidTarget => "invalid request target",
+            Self::InvalidVersion => "invalid http version",
+            Self::UnsupportedVersion => "unsupported http version",
+            Self::InvalidHeader => "invalid header",
+            Self::InvalidContentLength => "invalid content-length",
+            Self::AmbiguousLength => "ambiguous request body length",
+            Self::UnsupportedTransferCoding => "unsupported transfer coding",
+            Self::InvalidTransferCoding => "invalid transfer coding",
+            Self::UriTooLong => "request line too long",
+            Self::HeaderTooLarge => "request header too large",
+        })
+    }
+}
+
+impl IntoResponse for ParseError {
+    fn into_response(self) -> Response {
+        (self.status(), self.to_string()).into_response()
+    }
+}
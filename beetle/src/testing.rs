@@ -0,0 +1,126 @@
+//! test utility
+use bytes::{Bytes, BytesMut};
+use futures_core::Stream;
+use std::{
+    collections::VecDeque,
+    future::poll_fn,
+    io,
+    pin::{Pin, pin},
+    task::{Context, Poll, Waker},
+};
+
+use crate::{
+    headers::{HeaderMap, HeaderName, HeaderValue},
+    http::{Extensions, Method, Uri, Version},
+    request::{self, Parts, Request},
+    response::Response,
+    service::HttpService,
+};
+
+#[cfg(feature = "tokio")]
+use crate::service::tcp::Config;
+
+/// poll future to completion, the future must not wait for io
+pub(crate) fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let mut cx = Context::from_waker(Waker::noop());
+    for _ in 0..1024 {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+    }
+    panic!("future is still pending");
+}
+
+/// request without body
+pub(crate) fn request(method: Method, uri: &'static str, headers: &[(&'static str, &'static str)]) -> Request {
+    let mut map = HeaderMap::new();
+    for &(name, value) in headers {
+        map.append(HeaderName::new(name), HeaderValue::from_static(value));
+    }
+    let parts = Parts::new(method, Uri::from_static(uri), Version::V11, map, Extensions::default());
+    Request::from_parts(parts, request::Body::empty())
+}
+
+pub(crate) fn call<S: HttpService>(service: &S, req: Request) -> Response {
+    let Ok(response) = block_on(service.call(req));
+    response
+}
+
+/// collect response body
+pub(crate) fn body(response: Response) -> Bytes {
+    let mut body = response.into_body();
+    let mut bytes = BytesMut::new();
+    block_on(poll_fn(|cx| {
+        while let Some(data) = std::task::ready!(body.poll_data(cx)) {
+            bytes.extend_from_slice(&data.unwrap());
+        }
+        Poll::Ready(())
+    }));
+    bytes.freeze()
+}
+
+/// stream which yield each chunk
+pub(crate) fn stream(chunks: &[&'static str]) -> impl Stream<Item = io::Result<Bytes>> + Send + 'static {
+    struct Chunks(VecDeque<Bytes>);
+
+    impl Stream for Chunks {
+        type Item = io::Result<Bytes>;
+
+        fn poll_next(mut self: Pin<&mut Self>, _: &mut Context) -> Poll<Option<Self::Item>> {
+            Poll::Ready(self.0.pop_front().map(Ok))
+        }
+    }
+
+    Chunks(chunks.iter().map(|&chunk| Bytes::from_static(chunk.as_bytes())).collect())
+}
+
+/// serve a single connection which receive `input`, returns everything written by the server
+/// until it close the connection
+#[cfg(feature = "tokio")]
+pub(crate) fn serve<S>(service: S, config: Config, input: &[u8]) -> Vec<u8>
+where
+    S: HttpService + Clone,
+{
+    use tokio::io::AsyncWriteExt;
+
+    connect(service, config, async |mut client| {
+        client.write_all(input).await.unwrap();
+        read_to_end(&mut client).await
+    })
+}
+
+/// serve a single connection, `f` is called with the client side of the connection
+#[cfg(feature = "tokio")]
+pub(crate) fn connect<S, F, T>(service: S, config: Config, f: F) -> T
+where
+    S: HttpService + Clone,
+    F: AsyncFnOnce(tokio::net::TcpStream) -> T,
+{
+    use crate::{net::Socket, runtime::Tokio, service::{Service, tcp::TcpService}};
+    use tokio::net::{TcpListener, TcpStream};
+
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+    runtime.block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).await.unwrap();
+        let (io, _) = listener.accept().await.unwrap();
+        tokio::spawn(TcpService::new::<Tokio>(service, config).call(Socket::from(io)));
+        f(client).await
+    })
+}
+
+/// read until the server close the connection
+#[cfg(feature = "tokio")]
+pub(crate) async fn read_to_end(client: &mut tokio::net::TcpStream) -> Vec<u8> {
+    use std::time::Duration;
+    use tokio::io::AsyncReadExt;
+
+    let mut output = Vec::new();
+    let read = client.read_to_end(&mut output);
+    // connection reset by unread input is also treated as closed
+    let _ = tokio::time::timeout(Duration::from_secs(5), read)
+        .await
+        .expect("connection is not closed");
+    output
+}